@@ -0,0 +1,649 @@
+// Offline stand-in for everything a test run talks to: the faucets, an
+// Esplora-style chain API, Solana JSON-RPC and the borrower CLI itself.
+//
+// Run it next to the server and start runs with the "mock" network profile:
+//
+//   lava-mock-stack                      # listens on 127.0.0.1:8090
+//   curl -X POST localhost:8080/run-test -d '{"profile": "mock"}' -H 'content-type: application/json'
+//
+// The CLI served at /cli/loans-borrower-cli is this same binary. When it is
+// executed under the name `loans-borrower-cli` it acts as a fake borrower CLI
+// that forwards its arguments to the mock stack at MOCK_STACK_URL, so contract
+// state lives in one place and can be scripted with MOCK_CLI_SCRIPT.
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder, http::header, middleware::Logger};
+use chrono::{DateTime, Utc};
+use log::info;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// The fake CLI derives the wallet's collateral address the same way the server does
+#[path = "../wallet.rs"]
+#[allow(dead_code)]
+mod wallet;
+
+const DEFAULT_BIND: &str = "127.0.0.1:8090";
+const FAKE_CLI_NAME: &str = "loans-borrower-cli";
+const MOCK_CLI_VERSION: &str = "loans-borrower-cli 0.0.0-mock";
+
+// LavaUSD amount credited by the mock faucet when the request names none
+const DEFAULT_LAVA_USD_AMOUNT: u64 = 10;
+const LAVA_USD_DECIMALS: u32 = 6;
+
+// Open contracts at or above this LTV are liquidated once they are this old
+const DEFAULT_LIQUIDATION_LTV_BP: u64 = 9_000;
+const DEFAULT_LIQUIDATION_AFTER_SECS: i64 = 5;
+
+// Real length of a loan day; shorten it to let contracts expire during a test
+const DEFAULT_SECONDS_PER_DAY: i64 = 86_400;
+
+// Loan terms borrow init accepts; anything else is refused like bad CLI input
+const CAPITAL_ASSETS: &[&str] = &["solana-lava-usd"];
+const MAX_LTV_RATIO_BP: u64 = 9_999;
+
+// Request and response exchanged between the fake CLI and the mock stack
+#[derive(Debug, Serialize, Deserialize)]
+struct CliInvocation {
+    args: Vec<String>,
+    // SHA-256 of the MNEMONIC the CLI was started with; the mnemonic itself never leaves the process
+    wallet: Option<String>,
+    // Hex scriptPubKey of the wallet's BIP84 address, where its collateral is funded
+    #[serde(default)]
+    collateral_script: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CliOutcome {
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+    // File the CLI should write, relative to its working directory
+    output_file: Option<(String, String)>,
+    // Delay before the CLI exits, used to simulate a hanging process
+    delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Utxo {
+    txid: String,
+    vout: u32,
+    value: u64,
+    height: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Contract {
+    id: String,
+    wallet: Option<String>,
+    state: String,
+    loan: Value,
+    outcome: Value,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl Contract {
+    // Liquidates an open high-LTV contract once it has been left alone long
+    // enough, and defaults one that is still open when its term ends
+    fn settle(&mut self, liquidation_ltv_bp: u64, liquidation_after_secs: i64) {
+        let ltv_ratio_bp = self.loan["ltv_ratio_bp"].as_u64().unwrap_or(0);
+        let age = Utc::now() - self.created_at;
+        if self.state == "Open" && ltv_ratio_bp >= liquidation_ltv_bp && age.num_seconds() >= liquidation_after_secs {
+            self.state = "Closed".to_string();
+            self.outcome = json!({
+                "liquidation": {
+                    "collateral_liquidation_txid": random_hex(32),
+                    "liquidated_at": Utc::now().to_rfc3339(),
+                }
+            });
+        }
+        if self.state == "Open" && Utc::now() >= self.expires_at {
+            self.state = "Closed".to_string();
+            self.outcome = json!({
+                "default": {
+                    "collateral_liquidation_txid": random_hex(32),
+                    "defaulted_at": Utc::now().to_rfc3339(),
+                }
+            });
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut contract = json!({
+            "contract_id": self.id,
+            "loan": self.loan,
+            "outcome": self.outcome,
+            "expires_at": self.expires_at.to_rfc3339(),
+        });
+        contract[self.state.as_str()] = json!({ "updated_at": Utc::now().to_rfc3339() });
+        contract
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    tip_height: u64,
+    utxos: HashMap<String, Vec<Utxo>>,
+    token_balances: HashMap<String, u64>,
+    contracts: HashMap<String, Contract>,
+    faucet_calls: HashMap<String, u32>,
+    // Scripted behavior per CLI step, from MOCK_CLI_SCRIPT
+    cli_script: HashMap<String, String>,
+    faucet_fail_first: u32,
+    lava_usd_mint: String,
+    liquidation_ltv_bp: u64,
+    liquidation_after_secs: i64,
+    seconds_per_day: i64,
+}
+
+type SharedState = web::Data<Mutex<MockState>>;
+
+fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes).map(|_| format!("{:02x}", rng.gen::<u8>())).collect()
+}
+
+// Parses "step:action,step:action" into a lookup table
+fn parse_cli_script(script: &str) -> HashMap<String, String> {
+    script
+        .split(',')
+        .filter_map(|entry| entry.split_once(':'))
+        .map(|(step, action)| (step.trim().to_string(), action.trim().to_string()))
+        .collect()
+}
+
+// Fails the first MOCK_FAUCET_FAIL_FIRST calls of each faucet with 503 + Retry-After
+fn scripted_faucet_failure(state: &mut MockState, faucet: &str) -> Option<HttpResponse> {
+    let calls = state.faucet_calls.entry(faucet.to_string()).or_insert(0);
+    *calls += 1;
+    if *calls <= state.faucet_fail_first {
+        return Some(
+            HttpResponse::ServiceUnavailable()
+                .insert_header(("Retry-After", "1"))
+                .json(json!({ "error": "scripted faucet failure" })),
+        );
+    }
+    None
+}
+
+#[derive(Debug, Deserialize)]
+struct BtcFaucetRequest {
+    address: String,
+    sats: u64,
+}
+
+async fn mint_btc(state: SharedState, req: web::Json<BtcFaucetRequest>) -> impl Responder {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = scripted_faucet_failure(&mut state, "btc") {
+        return response;
+    }
+
+    // Mine the faucet transaction straight into the next block
+    state.tip_height += 1;
+    let utxo = Utxo {
+        txid: random_hex(32),
+        vout: 0,
+        value: req.sats,
+        height: state.tip_height,
+    };
+    info!("Minted {} sats to {} in {}", req.sats, req.address, utxo.txid);
+    state.utxos.entry(req.address.clone()).or_default().push(utxo.clone());
+
+    HttpResponse::Ok().json(json!({ "txid": utxo.txid }))
+}
+
+#[derive(Debug, Deserialize)]
+struct LavaUsdFaucetRequest {
+    pubkey: String,
+    amount: Option<u64>,
+}
+
+async fn transfer_lava_usd(state: SharedState, req: web::Json<LavaUsdFaucetRequest>) -> impl Responder {
+    let mut state = state.lock().unwrap();
+    if let Some(response) = scripted_faucet_failure(&mut state, "lava_usd") {
+        return response;
+    }
+
+    let amount = req.amount.unwrap_or(DEFAULT_LAVA_USD_AMOUNT) * 10u64.pow(LAVA_USD_DECIMALS);
+    *state.token_balances.entry(req.pubkey.clone()).or_insert(0) += amount;
+    info!("Transferred {} raw LavaUSD units to {}", amount, req.pubkey);
+
+    HttpResponse::Ok().json(json!({ "signature": random_hex(32) }))
+}
+
+async fn tip_height(state: SharedState) -> impl Responder {
+    HttpResponse::Ok().body(state.lock().unwrap().tip_height.to_string())
+}
+
+async fn address_utxos(state: SharedState, path: web::Path<String>) -> impl Responder {
+    let state = state.lock().unwrap();
+    let utxos: Vec<Value> = state
+        .utxos
+        .get(&path.into_inner())
+        .into_iter()
+        .flatten()
+        .map(|utxo| {
+            json!({
+                "txid": utxo.txid,
+                "vout": utxo.vout,
+                "value": utxo.value,
+                "status": { "confirmed": true, "block_height": utxo.height },
+            })
+        })
+        .collect();
+    HttpResponse::Ok().json(utxos)
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+async fn solana_rpc(state: SharedState, req: web::Json<RpcRequest>) -> impl Responder {
+    let state = state.lock().unwrap();
+    let context = json!({ "slot": state.tip_height });
+
+    // Token accounts are modelled as "<owner>-lava-usd"
+    let result = match req.method.as_str() {
+        "getTokenAccountsByOwner" => {
+            let owner = req.params.first().and_then(Value::as_str).unwrap_or_default();
+            let mint = req.params.get(1).and_then(|filter| filter["mint"].as_str()).unwrap_or_default();
+            let accounts = if mint == state.lava_usd_mint && state.token_balances.contains_key(owner) {
+                vec![json!({ "pubkey": format!("{}-lava-usd", owner), "account": {} })]
+            } else {
+                Vec::new()
+            };
+            json!({ "context": context, "value": accounts })
+        }
+        "getTokenAccountBalance" => {
+            let account = req.params.first().and_then(Value::as_str).unwrap_or_default();
+            let owner = account.trim_end_matches("-lava-usd");
+            let amount = state.token_balances.get(owner).copied().unwrap_or(0);
+            json!({
+                "context": context,
+                "value": {
+                    "amount": amount.to_string(),
+                    "decimals": LAVA_USD_DECIMALS,
+                    "uiAmountString": (amount as f64 / 10f64.powi(LAVA_USD_DECIMALS as i32)).to_string(),
+                },
+            })
+        }
+        method => {
+            return HttpResponse::Ok().json(json!({
+                "jsonrpc": "2.0",
+                "id": req.id,
+                "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+            }));
+        }
+    };
+
+    HttpResponse::Ok().json(json!({ "jsonrpc": "2.0", "id": req.id, "result": result }))
+}
+
+// This executable as served to clients, read and hashed once at startup
+struct CliDownload {
+    bytes: web::Bytes,
+    etag: String,
+}
+
+impl CliDownload {
+    fn load() -> std::io::Result<Self> {
+        let bytes = env::current_exe().and_then(fs::read)?;
+        let etag = format!("\"{}\"", hex::encode(Sha256::digest(&bytes)));
+        Ok(Self { bytes: bytes.into(), etag })
+    }
+}
+
+// Serves this executable as the borrower CLI download, with an ETag so
+// clients can revalidate their cached copy
+async fn download_cli(req: HttpRequest, cli: web::Data<CliDownload>) -> impl Responder {
+    let if_none_match = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok());
+    if if_none_match == Some(cli.etag.as_str()) {
+        return HttpResponse::NotModified().insert_header((header::ETAG, cli.etag.clone())).finish();
+    }
+
+    HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .insert_header((header::ETAG, cli.etag.clone()))
+        .body(cli.bytes.clone())
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+// Why borrow init refuses the loan terms, if it does
+fn invalid_loan_terms(args: &[String]) -> Option<String> {
+    let capital_asset = flag_value(args, "--loan-capital-asset").unwrap_or_default();
+    if !CAPITAL_ASSETS.contains(&capital_asset) {
+        return Some(format!("unsupported loan capital asset '{}'", capital_asset));
+    }
+    let ltv_ratio_bp = flag_value(args, "--ltv-ratio-bp").and_then(|v| v.parse::<u64>().ok());
+    if !ltv_ratio_bp.is_some_and(|ltv| (1..=MAX_LTV_RATIO_BP).contains(&ltv)) {
+        return Some(format!("--ltv-ratio-bp must be between 1 and {}", MAX_LTV_RATIO_BP));
+    }
+    let amount = flag_value(args, "--loan-amount").and_then(|v| v.parse::<f64>().ok());
+    if !amount.is_some_and(|amount| amount > 0.0) {
+        return Some("--loan-amount must be greater than 0".to_string());
+    }
+    None
+}
+
+// Whether any faucet-funded address pays to the given scriptPubKey
+fn holds_collateral(state: &MockState, script: &str) -> bool {
+    state.utxos.iter().any(|(address, utxos)| {
+        !utxos.is_empty()
+            && address
+                .parse::<bitcoin::Address<bitcoin::address::NetworkUnchecked>>()
+                .is_ok_and(|address| hex::encode(address.assume_checked().script_pubkey().as_bytes()) == script)
+    })
+}
+
+fn cli_error(exit_code: i32, message: String) -> CliOutcome {
+    CliOutcome {
+        exit_code,
+        stderr: format!("Error: {}\n", message),
+        ..Default::default()
+    }
+}
+
+// Emulates one borrower CLI invocation against the mock state
+fn run_cli(state: &mut MockState, invocation: &CliInvocation) -> CliOutcome {
+    let args = &invocation.args;
+    if args.iter().any(|arg| arg == "--version") {
+        return CliOutcome {
+            stdout: format!("{}\n", MOCK_CLI_VERSION),
+            ..Default::default()
+        };
+    }
+
+    // Global flags come before the subcommand
+    let command: Vec<&str> = args
+        .iter()
+        .skip_while(|arg| arg.starts_with("--"))
+        .take_while(|arg| !arg.starts_with("--"))
+        .map(String::as_str)
+        .collect();
+    let step = command.join("-");
+
+    let action = state.cli_script.get(&step).cloned().unwrap_or_else(|| "ok".to_string());
+    match action.as_str() {
+        "ok" => {}
+        "fail" => return cli_error(1, format!("scripted failure of {}", step)),
+        "hang" => {
+            return CliOutcome {
+                delay_ms: 3_600_000,
+                ..Default::default()
+            }
+        }
+        _ => {}
+    }
+
+    match step.as_str() {
+        "borrow-init" => {
+            if let Some(reason) = invalid_loan_terms(args) {
+                return cli_error(2, reason);
+            }
+            if let Some(script) = &invocation.collateral_script {
+                if !holds_collateral(state, script) {
+                    return cli_error(1, "insufficient funds: the wallet holds no BTC to lock as collateral".to_string());
+                }
+            }
+
+            let id = random_hex(16);
+            let loan = json!({
+                "capital_asset": flag_value(args, "--loan-capital-asset"),
+                "ltv_ratio_bp": flag_value(args, "--ltv-ratio-bp").and_then(|v| v.parse::<u64>().ok()),
+                "duration_days": flag_value(args, "--loan-duration-days").and_then(|v| v.parse::<u64>().ok()),
+                "amount": flag_value(args, "--loan-amount").and_then(|v| v.parse::<f64>().ok()),
+            });
+            let term_secs = loan["duration_days"].as_i64().unwrap_or(0) * state.seconds_per_day;
+            let finalize = args.iter().any(|arg| arg == "--finalize");
+            state.contracts.insert(id.clone(), Contract {
+                id: id.clone(),
+                wallet: invocation.wallet.clone(),
+                state: if finalize { "Open" } else { "Requested" }.to_string(),
+                loan,
+                outcome: Value::Null,
+                created_at: Utc::now(),
+                expires_at: Utc::now() + chrono::Duration::seconds(term_secs),
+            });
+
+            let mut stdout = String::from("Requesting loan offer...\nLoan offer accepted\n");
+            if action != "no-contract-id" {
+                stdout.push_str(&format!("contract-id: {}\n", id));
+            }
+            if finalize {
+                stdout.push_str("Loan finalized, collateral locked\n");
+            }
+            CliOutcome {
+                stdout,
+                ..Default::default()
+            }
+        }
+        "borrow-finalize" => {
+            let id = flag_value(args, "--contract-id").unwrap_or_default().to_string();
+            let contract = match state.contracts.get_mut(&id) {
+                Some(contract) => contract,
+                None => return cli_error(1, format!("contract {} not found", id)),
+            };
+            if contract.wallet != invocation.wallet {
+                return cli_error(1, format!("contract {} does not belong to this wallet", id));
+            }
+            if contract.state != "Requested" {
+                return cli_error(1, format!("contract {} is {}, not awaiting finalization", id, contract.state));
+            }
+
+            // Reports success but leaves the contract requested
+            if action != "no-open" {
+                contract.state = "Open".to_string();
+            }
+            CliOutcome {
+                stdout: format!("Finalizing loan {}...\nLoan finalized, collateral locked\n", id),
+                ..Default::default()
+            }
+        }
+        "borrow-repay" => {
+            let id = flag_value(args, "--contract-id").unwrap_or_default().to_string();
+            let contract = match state.contracts.get_mut(&id) {
+                Some(contract) => contract,
+                None => return cli_error(1, format!("contract {} not found", id)),
+            };
+            if contract.wallet != invocation.wallet {
+                return cli_error(1, format!("contract {} does not belong to this wallet", id));
+            }
+
+            match action.as_str() {
+                // Reports success but leaves the contract open
+                "no-close" => {}
+                "no-txid" => {
+                    contract.state = "Closed".to_string();
+                    contract.outcome = json!({ "repayment": { "repaid_at": Utc::now().to_rfc3339() } });
+                }
+                _ => {
+                    contract.state = "Closed".to_string();
+                    contract.outcome = json!({
+                        "repayment": {
+                            "collateral_repayment_txid": random_hex(32),
+                            "repaid_at": Utc::now().to_rfc3339(),
+                        }
+                    });
+                }
+            }
+            CliOutcome {
+                stdout: format!("Repaying loan {}...\nLoan repaid, collateral returned\n", id),
+                ..Default::default()
+            }
+        }
+        "get-contract" => {
+            let id = flag_value(args, "--contract-id").unwrap_or_default().to_string();
+            let (liquidation_ltv_bp, liquidation_after_secs) = (state.liquidation_ltv_bp, state.liquidation_after_secs);
+            let contract = match state.contracts.get_mut(&id) {
+                Some(contract) => contract,
+                None => return cli_error(1, format!("contract {} not found", id)),
+            };
+            contract.settle(liquidation_ltv_bp, liquidation_after_secs);
+
+            let content = match action.as_str() {
+                "bad-json" => "{ not json".to_string(),
+                _ => serde_json::to_string_pretty(&contract.to_json()).unwrap_or_default(),
+            };
+            let output_file = match (action.as_str(), flag_value(args, "--output-file")) {
+                ("no-file", _) | (_, None) => None,
+                (_, Some(path)) => Some((path.to_string(), content.clone())),
+            };
+            let stdout = if args.iter().any(|arg| arg == "--verbose") {
+                format!("{}\n", content)
+            } else {
+                format!("Contract {} is {}\n", id, contract.state)
+            };
+            CliOutcome {
+                stdout,
+                output_file,
+                ..Default::default()
+            }
+        }
+        _ => cli_error(2, format!("unrecognized subcommand '{}'", command.join(" "))),
+    }
+}
+
+async fn invoke_cli(state: SharedState, req: web::Json<CliInvocation>) -> impl Responder {
+    let outcome = run_cli(&mut state.lock().unwrap(), &req);
+    info!("Fake CLI {:?} exited with {}", req.args, outcome.exit_code);
+    HttpResponse::Ok().json(outcome)
+}
+
+// Entry point when this binary runs as the borrower CLI
+fn run_fake_cli() -> i32 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let base_url = env::var("MOCK_STACK_URL").unwrap_or_else(|_| format!("http://{}", DEFAULT_BIND));
+    let mnemonic = env::var("MNEMONIC").ok();
+    let wallet = mnemonic
+        .as_ref()
+        .map(|mnemonic| hex::encode(Sha256::digest(mnemonic.as_bytes())));
+    // The script of a P2WPKH address does not depend on the test network it is encoded for
+    let collateral_script = mnemonic
+        .and_then(|mnemonic| bip39::Mnemonic::parse(mnemonic).ok())
+        .and_then(|mnemonic| wallet::derive_btc_address(&mnemonic, bitcoin::Network::Testnet).ok())
+        .and_then(|receive| receive.address.parse::<bitcoin::Address<bitcoin::address::NetworkUnchecked>>().ok())
+        .map(|address| hex::encode(address.assume_checked().script_pubkey().as_bytes()));
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Error: failed to start runtime: {}", e);
+            return 1;
+        }
+    };
+
+    let outcome: Result<CliOutcome, reqwest::Error> = runtime.block_on(async {
+        reqwest::Client::new()
+            .post(format!("{}/mock/cli", base_url.trim_end_matches('/')))
+            .json(&CliInvocation { args, wallet, collateral_script })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    });
+
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("Error: mock stack unreachable at {}: {}", base_url, e);
+            return 1;
+        }
+    };
+
+    if outcome.delay_ms > 0 {
+        std::thread::sleep(Duration::from_millis(outcome.delay_ms));
+    }
+    if let Some((path, content)) = &outcome.output_file {
+        if let Err(e) = fs::write(path, content) {
+            eprintln!("Error: failed to write {}: {}", path, e);
+            return 1;
+        }
+    }
+    print!("{}", outcome.stdout);
+    eprint!("{}", outcome.stderr);
+    outcome.exit_code
+}
+
+async fn serve() -> std::io::Result<()> {
+    let bind_address = env::var("MOCK_BIND_ADDRESS").unwrap_or_else(|_| DEFAULT_BIND.to_string());
+
+    let state = web::Data::new(Mutex::new(MockState {
+        tip_height: 100,
+        cli_script: parse_cli_script(&env::var("MOCK_CLI_SCRIPT").unwrap_or_default()),
+        faucet_fail_first: env::var("MOCK_FAUCET_FAIL_FIRST")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0),
+        lava_usd_mint: env::var("MOCK_LAVA_USD_MINT")
+            .unwrap_or_else(|_| "MockLavaUsdMint111111111111111111111111111".to_string()),
+        liquidation_ltv_bp: env::var("MOCK_LIQUIDATION_LTV_BP")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_LIQUIDATION_LTV_BP),
+        liquidation_after_secs: env::var("MOCK_LIQUIDATION_AFTER_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_LIQUIDATION_AFTER_SECS),
+        seconds_per_day: env::var("MOCK_SECONDS_PER_DAY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_SECONDS_PER_DAY),
+        ..Default::default()
+    }));
+
+    let cli_download = web::Data::new(CliDownload::load()?);
+
+    info!("Mock stack listening on {}", bind_address);
+
+    HttpServer::new(move || {
+        App::new()
+            .wrap(Logger::default())
+            .app_data(state.clone())
+            .app_data(cli_download.clone())
+            .route("/faucet/mint-mutinynet", web::post().to(mint_btc))
+            .route("/faucet/transfer-lava-usd", web::post().to(transfer_lava_usd))
+            .route("/esplora/blocks/tip/height", web::get().to(tip_height))
+            .route("/esplora/address/{address}/utxo", web::get().to(address_utxos))
+            .route("/solana", web::post().to(solana_rpc))
+            .route("/cli/loans-borrower-cli", web::get().to(download_cli))
+            .route("/mock/cli", web::post().to(invoke_cli))
+    })
+    // Several runs downloading the (large, debug-built) CLI at once on a busy
+    // CI machine can delay reading a request well past actix's 5s default
+    .client_request_timeout(Duration::from_secs(60))
+    .bind(&bind_address)?
+    .run()
+    .await
+}
+
+fn main() -> std::io::Result<()> {
+    let invoked_as = env::args().next().unwrap_or_default();
+    if Path::new(&invoked_as).file_name().and_then(|name| name.to_str()) == Some(FAKE_CLI_NAME) {
+        std::process::exit(run_fake_cli());
+    }
+
+    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+    actix_web::rt::System::new().block_on(serve())
+}
@@ -0,0 +1,265 @@
+use anyhow::{Context, Result, anyhow};
+use log::info;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+#[derive(Debug, Clone, Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Utxo {
+    txid: String,
+    vout: u32,
+    value: u64,
+    status: TxStatus,
+}
+
+// A transaction output, as its txid and output index
+pub type Outpoint = (String, u32);
+
+// The UTXO that funded the borrower's BTC address
+#[derive(Debug, Clone, Serialize)]
+pub struct Funding {
+    pub txid: String,
+    pub vout: u32,
+    pub value: u64,
+    pub block_height: Option<u64>,
+    pub confirmations: u64,
+}
+
+// How long and how deep to wait for the faucet transaction
+#[derive(Debug, Clone)]
+pub struct FundingWait {
+    pub min_value: u64,
+    // UTXOs the address held before the faucet call; they never count as the
+    // funding, so a replayed wallet cannot pass on an earlier run's coins
+    pub existing: HashSet<Outpoint>,
+    pub confirmations: u64,
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+// Minimal client for an Esplora-compatible HTTP API
+pub struct EsploraClient {
+    client: Client,
+    base_url: String,
+}
+
+impl EsploraClient {
+    pub fn new(client: Client, base_url: &str) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn tip_height(&self) -> Result<u64> {
+        let url = format!("{}/blocks/tip/height", self.base_url);
+        let body = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to fetch {}", url))?
+            .text()
+            .await
+            .context("Failed to read tip height")?;
+        body.trim().parse().with_context(|| format!("Invalid tip height '{}'", body.trim()))
+    }
+
+    async fn address_utxos(&self, address: &str) -> Result<Vec<Utxo>> {
+        let url = format!("{}/address/{}/utxo", self.base_url, address);
+        self.client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to fetch {}", url))?
+            .json()
+            .await
+            .context("Failed to parse address UTXOs")
+    }
+
+    // Outpoints of the UTXOs the address holds right now
+    pub async fn outpoints(&self, address: &str) -> Result<HashSet<Outpoint>> {
+        let utxos = self.address_utxos(address).await?;
+        Ok(utxos.into_iter().map(|utxo| (utxo.txid, utxo.vout)).collect())
+    }
+
+    // Current funding of the address, if a new UTXO carries at least min_value
+    async fn find_funding(&self, address: &str, wait: &FundingWait) -> Result<Option<Funding>> {
+        let utxos = self.address_utxos(address).await?;
+        let utxo = utxos
+            .into_iter()
+            .find(|utxo| utxo.value >= wait.min_value && !wait.existing.contains(&(utxo.txid.clone(), utxo.vout)));
+        let utxo = match utxo {
+            Some(utxo) => utxo,
+            None => return Ok(None),
+        };
+
+        let confirmations = match (utxo.status.confirmed, utxo.status.block_height) {
+            (true, Some(height)) => self.tip_height().await?.saturating_sub(height) + 1,
+            _ => 0,
+        };
+
+        Ok(Some(Funding {
+            txid: utxo.txid,
+            vout: utxo.vout,
+            value: utxo.value,
+            block_height: utxo.status.block_height,
+            confirmations,
+        }))
+    }
+
+    // Polls the address until the funding UTXO reaches the requested depth.
+    // Transient API errors are logged and retried until the timeout.
+    pub async fn wait_for_funding(&self, address: &str, wait: &FundingWait) -> Result<Funding> {
+        let started = Instant::now();
+        let mut last_seen: Option<Funding> = None;
+        let mut last_error: Option<String> = None;
+
+        loop {
+            match self.find_funding(address, wait).await {
+                Ok(Some(funding)) if funding.confirmations >= wait.confirmations => return Ok(funding),
+                Ok(Some(funding)) => {
+                    info!(
+                        "Funding tx {} has {}/{} confirmations",
+                        funding.txid, funding.confirmations, wait.confirmations
+                    );
+                    last_seen = Some(funding);
+                }
+                Ok(None) => info!("No funding UTXO for {} yet", address),
+                Err(e) => {
+                    info!("Chain API error while waiting for funding: {}", e);
+                    last_error = Some(e.to_string());
+                }
+            }
+
+            if started.elapsed() + wait.poll_interval > wait.timeout {
+                let state = match (last_seen, last_error) {
+                    (Some(funding), _) => format!(
+                        "funding tx {} only reached {}/{} confirmations",
+                        funding.txid, funding.confirmations, wait.confirmations
+                    ),
+                    (None, Some(error)) => format!("no funding UTXO seen (last error: {})", error),
+                    (None, None) => "no funding UTXO seen".to_string(),
+                };
+                return Err(anyhow!(
+                    "Timed out after {}s waiting for BTC funding of {}: {}",
+                    wait.timeout.as_secs(),
+                    address,
+                    state
+                ));
+            }
+
+            sleep(wait.poll_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{Response, ScriptedServer};
+    use serde_json::{Value, json};
+
+    const ADDRESS: &str = "bcrt1q6rz28mcfaxtmd6v789l9rrlrusdprr9pz3cppk";
+
+    fn utxo(txid: &str, value: u64, height: Option<u64>) -> Value {
+        json!({
+            "txid": txid,
+            "vout": 0,
+            "value": value,
+            "status": { "confirmed": height.is_some(), "block_height": height },
+        })
+    }
+
+    // Esplora stand-in at tip height 110 whose address answers the listed
+    // UTXO sets in turn, repeating the last
+    async fn esplora(utxo_sets: Vec<Vec<Value>>) -> ScriptedServer {
+        let polls = std::sync::atomic::AtomicUsize::new(0);
+        ScriptedServer::start(move |_, request| match request.path.as_str() {
+            "/blocks/tip/height" => Response::status(200).body("110"),
+            path if path == format!("/address/{}/utxo", ADDRESS) => {
+                let poll = polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Response::json(200, json!(utxo_sets[poll.min(utxo_sets.len() - 1)]))
+            }
+            _ => Response::status(404),
+        })
+        .await
+    }
+
+    fn wait(existing: &[&str], confirmations: u64) -> FundingWait {
+        FundingWait {
+            min_value: 100_000,
+            existing: existing.iter().map(|txid| (txid.to_string(), 0)).collect(),
+            confirmations,
+            timeout: Duration::from_millis(500),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn waits_until_the_funding_confirms() {
+        let server = esplora(vec![
+            vec![],
+            vec![utxo("faucet", 100_000, None)],
+            vec![utxo("faucet", 100_000, Some(109))],
+        ])
+        .await;
+        let chain = EsploraClient::new(Client::new(), &format!("{}/", server.url));
+
+        let funding = chain.wait_for_funding(ADDRESS, &wait(&[], 2)).await.unwrap();
+
+        assert_eq!(funding.txid, "faucet");
+        assert_eq!(funding.block_height, Some(109));
+        assert_eq!(funding.confirmations, 2);
+    }
+
+    #[tokio::test]
+    async fn ignores_utxos_that_were_there_before_the_faucet_call() {
+        let earlier_run = utxo("earlier-run", 500_000, Some(50));
+        let server = esplora(vec![
+            vec![earlier_run.clone()],
+            vec![earlier_run.clone(), utxo("faucet", 100_000, Some(110))],
+        ])
+        .await;
+        let chain = EsploraClient::new(Client::new(), &server.url);
+
+        let existing = chain.outpoints(ADDRESS).await.unwrap();
+        assert_eq!(existing, HashSet::from([("earlier-run".to_string(), 0)]));
+
+        let funding = chain
+            .wait_for_funding(ADDRESS, &FundingWait { existing, ..wait(&[], 1) })
+            .await
+            .unwrap();
+        assert_eq!(funding.txid, "faucet");
+    }
+
+    #[tokio::test]
+    async fn times_out_when_only_old_or_small_utxos_show_up() {
+        let server = esplora(vec![vec![utxo("earlier-run", 500_000, Some(50)), utxo("dust", 99_999, Some(110))]]).await;
+        let chain = EsploraClient::new(Client::new(), &server.url);
+
+        let error = chain.wait_for_funding(ADDRESS, &wait(&["earlier-run"], 1)).await.unwrap_err();
+
+        assert!(error.to_string().contains("no funding UTXO seen"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn reports_the_confirmations_reached_on_timeout() {
+        let server = esplora(vec![vec![utxo("faucet", 100_000, Some(110))]]).await;
+        let chain = EsploraClient::new(Client::new(), &server.url);
+
+        let error = chain.wait_for_funding(ADDRESS, &wait(&[], 3)).await.unwrap_err();
+
+        assert!(error.to_string().contains("faucet only reached 1/3 confirmations"), "{}", error);
+    }
+}
@@ -0,0 +1,365 @@
+use log::{error, warn};
+use std::ffi::OsStr;
+use std::io;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::time::timeout;
+
+// Flags passed to every borrower CLI invocation, after the network flag
+const GLOBAL_ARGS: &[&str] = &["--disable-backup-contracts"];
+
+// How long to keep reading output after the CLI process has exited
+const PIPE_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Builds borrower CLI invocations without going through a shell.
+// Arguments are passed as an argv vector and the mnemonic via the environment,
+// so values coming from CLI output (like contract IDs) are never interpreted.
+// Every invocation runs inside the run's work directory, which is also its HOME.
+pub struct BorrowerCli {
+    exec: PathBuf,
+    mnemonic: String,
+    network_flag: String,
+    work_dir: PathBuf,
+}
+
+impl BorrowerCli {
+    pub fn new(exec: impl Into<PathBuf>, mnemonic: &str, network_flag: &str, work_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            exec: exec.into(),
+            mnemonic: mnemonic.to_string(),
+            network_flag: network_flag.to_string(),
+            work_dir: work_dir.into(),
+        }
+    }
+
+    // Full argv (excluding the executable) for the given subcommand arguments
+    pub fn args<S: AsRef<str>>(&self, args: &[S]) -> Vec<String> {
+        std::iter::once(self.network_flag.clone())
+            .chain(GLOBAL_ARGS.iter().map(|arg| arg.to_string()))
+            .chain(args.iter().map(|arg| arg.as_ref().to_string()))
+            .collect()
+    }
+
+    pub fn command<S: AsRef<str>>(&self, args: &[S]) -> Command {
+        let mut command = Command::new(&self.exec);
+        command
+            .args(self.args(args).iter().map(OsStr::new))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .current_dir(&self.work_dir)
+            .env("HOME", &self.work_dir)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("XDG_CACHE_HOME")
+            .env("MNEMONIC", &self.mnemonic);
+        // Own process group, so a timeout also kills anything the CLI started
+        #[cfg(unix)]
+        command.process_group(0);
+        command
+    }
+
+    // Runs one CLI step, killing its process group if it outlives `limit`
+    pub async fn run<S: AsRef<str>>(&self, args: &[S], limit: Duration) -> StepRun {
+        let started = Instant::now();
+        let mut child = match self.command(args).spawn() {
+            Ok(child) => child,
+            Err(e) => return StepRun { outcome: StepOutcome::SpawnFailed(e), duration: started.elapsed() },
+        };
+
+        let stdout = PipeReader::spawn(child.stdout.take());
+        let stderr = PipeReader::spawn(child.stderr.take());
+
+        let status = match timeout(limit, child.wait()).await {
+            Ok(Ok(status)) => Some(status),
+            Ok(Err(e)) => {
+                let _ = child.start_kill();
+                return StepRun { outcome: StepOutcome::SpawnFailed(e), duration: started.elapsed() };
+            }
+            Err(_) => {
+                warn!("{} timed out after {}s, killing it", self.describe(args), limit.as_secs());
+                kill_process_group(&mut child);
+                let _ = child.wait().await;
+                None
+            }
+        };
+
+        let stdout = stdout.drain(PIPE_DRAIN_TIMEOUT).await;
+        let stderr = stderr.drain(PIPE_DRAIN_TIMEOUT).await;
+        let duration = started.elapsed();
+        let outcome = match status {
+            Some(status) => StepOutcome::Exited { status, stdout, stderr },
+            None => StepOutcome::TimedOut { limit, stdout, stderr },
+        };
+        StepRun { outcome, duration }
+    }
+
+    // Printable form of an invocation; the mnemonic is never included
+    pub fn describe<S: AsRef<str>>(&self, args: &[S]) -> String {
+        std::iter::once(self.exec.display().to_string())
+            .chain(self.args(args).iter().map(|arg| quote_for_display(arg)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// How a CLI step ended
+#[derive(Debug)]
+pub enum StepOutcome {
+    Exited { status: ExitStatus, stdout: Vec<u8>, stderr: Vec<u8> },
+    // Killed after exceeding its time limit; output is whatever it wrote until then
+    TimedOut { limit: Duration, stdout: Vec<u8>, stderr: Vec<u8> },
+    SpawnFailed(io::Error),
+}
+
+#[derive(Debug)]
+pub struct StepRun {
+    pub outcome: StepOutcome,
+    pub duration: Duration,
+}
+
+impl StepRun {
+    pub fn succeeded(&self) -> bool {
+        matches!(&self.outcome, StepOutcome::Exited { status, .. } if status.success())
+    }
+
+    // Stable name of the outcome, recorded with the step
+    pub fn outcome_name(&self) -> &'static str {
+        match &self.outcome {
+            StepOutcome::Exited { status, .. } if status.success() => "succeeded",
+            StepOutcome::Exited { .. } => "failed",
+            StepOutcome::TimedOut { .. } => "timed_out",
+            StepOutcome::SpawnFailed(_) => "spawn_failed",
+        }
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        match &self.outcome {
+            StepOutcome::Exited { status, .. } => status.code(),
+            _ => None,
+        }
+    }
+
+    // Signal that terminated the process, if it did not exit on its own
+    pub fn signal(&self) -> Option<i32> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            match &self.outcome {
+                StepOutcome::Exited { status, .. } => status.signal(),
+                // Timed-out steps are killed with SIGKILL
+                StepOutcome::TimedOut { .. } => Some(libc::SIGKILL),
+                StepOutcome::SpawnFailed(_) => None,
+            }
+        }
+        #[cfg(not(unix))]
+        None
+    }
+
+    pub fn stdout(&self) -> String {
+        match &self.outcome {
+            StepOutcome::Exited { stdout, .. } | StepOutcome::TimedOut { stdout, .. } => {
+                String::from_utf8_lossy(stdout).to_string()
+            }
+            StepOutcome::SpawnFailed(_) => String::new(),
+        }
+    }
+
+    pub fn stderr(&self) -> String {
+        match &self.outcome {
+            StepOutcome::Exited { stderr, .. } | StepOutcome::TimedOut { stderr, .. } => {
+                String::from_utf8_lossy(stderr).to_string()
+            }
+            StepOutcome::SpawnFailed(e) => e.to_string(),
+        }
+    }
+}
+
+// Reads a pipe in the background into a buffer that stays readable while
+// the read is still in progress
+struct PipeReader {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl PipeReader {
+    fn spawn<R: AsyncRead + Unpin + Send + 'static>(pipe: Option<R>) -> Self {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let shared = buffer.clone();
+        let task = tokio::spawn(async move {
+            let Some(mut pipe) = pipe else {
+                return;
+            };
+            let mut chunk = [0u8; 8192];
+            while let Ok(read @ 1..) = pipe.read(&mut chunk).await {
+                shared.lock().unwrap().extend_from_slice(&chunk[..read]);
+            }
+        });
+        Self { buffer, task }
+    }
+
+    // Waits for the pipe to close, but not forever: it can outlive the process
+    // if the process left children behind. On timeout the read is aborted and
+    // whatever was read so far is returned.
+    async fn drain(mut self, limit: Duration) -> Vec<u8> {
+        if timeout(limit, &mut self.task).await.is_err() {
+            warn!("Output pipe still open {}s after the process ended, keeping the output read so far", limit.as_secs());
+            self.task.abort();
+        }
+        std::mem::take(&mut *self.buffer.lock().unwrap())
+    }
+}
+
+fn kill_process_group(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // The child leads its own group, so -pid addresses the whole group
+        if unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) } == 0 {
+            return;
+        }
+        error!("Failed to kill process group {}: {}", pid, io::Error::last_os_error());
+    }
+    let _ = child.start_kill();
+}
+
+fn quote_for_display(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '='));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("{:?}", arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTILE_CONTRACT_ID: &str = "abc$(touch pwned); rm -rf ~ 'x\" y`id`";
+    const HOSTILE_MNEMONIC: &str = "word $(touch pwned) ; echo \"leak\" 'quoted' two  spaces";
+
+    fn cli(exec: &str, work_dir: &std::path::Path) -> BorrowerCli {
+        BorrowerCli::new(exec, HOSTILE_MNEMONIC, "--testnet", work_dir)
+    }
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lava-cli-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn hostile_values_are_passed_as_single_arguments() {
+        let work_dir = PathBuf::from("/tmp/run");
+        let cli = cli("/opt/cli", &work_dir);
+        let command = cli.command(&["borrow", "repay", "--contract-id", HOSTILE_CONTRACT_ID]);
+        let command = command.as_std();
+
+        assert_eq!(command.get_program(), "/opt/cli");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            ["--testnet", "--disable-backup-contracts", "borrow", "repay", "--contract-id", HOSTILE_CONTRACT_ID]
+        );
+
+        // The mnemonic only travels in the environment, never on the command line
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&(OsStr::new("MNEMONIC"), Some(OsStr::new(HOSTILE_MNEMONIC)))));
+        assert!(envs.contains(&(OsStr::new("HOME"), Some(work_dir.as_os_str()))));
+        assert!(args.iter().all(|arg| !arg.to_string_lossy().contains("leak")));
+        assert_eq!(command.get_current_dir(), Some(work_dir.as_path()));
+    }
+
+    #[test]
+    fn describe_quotes_hostile_values_and_omits_the_mnemonic() {
+        let cli = cli("/opt/cli", std::path::Path::new("/tmp/run"));
+        let described = cli.describe(&["borrow", "repay", "--contract-id", HOSTILE_CONTRACT_ID]);
+
+        assert_eq!(
+            described,
+            format!("/opt/cli --testnet --disable-backup-contracts borrow repay --contract-id {:?}", HOSTILE_CONTRACT_ID)
+        );
+        assert!(!described.contains("leak"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn hostile_values_reach_the_cli_literally() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch_dir();
+        let script = dir.join("echo-args");
+        std::fs::write(&script, "#!/bin/sh\nfor arg in \"$@\"; do printf '%s\\n' \"$arg\"; done\nprintf '%s\\n' \"$MNEMONIC\"\n")
+            .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let cli = cli(script.to_str().unwrap(), &dir);
+        let run = cli.run(&["borrow", "repay", "--contract-id", HOSTILE_CONTRACT_ID], Duration::from_secs(10)).await;
+
+        assert!(run.succeeded(), "{}", run.stderr());
+        let lines: Vec<String> = run.stdout().lines().map(String::from).collect();
+        assert_eq!(
+            lines,
+            [
+                "--testnet",
+                "--disable-backup-contracts",
+                "borrow",
+                "repay",
+                "--contract-id",
+                HOSTILE_CONTRACT_ID,
+                HOSTILE_MNEMONIC,
+            ]
+        );
+        assert!(!dir.join("pwned").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn drain_keeps_partial_output_and_stops_reading_on_timeout() {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut writer, pipe) = tokio::io::duplex(64);
+        let reader = PipeReader::spawn(Some(pipe));
+        writer.write_all(b"partial output").await.unwrap();
+
+        let output = reader.drain(Duration::from_millis(200)).await;
+        assert_eq!(output, b"partial output");
+
+        // The aborted read drops its end of the pipe, so further writes fail
+        let mut closed = false;
+        for _ in 0..50 {
+            if writer.write_all(b"more").await.is_err() {
+                closed = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(closed, "reader task was not aborted");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn output_survives_a_child_holding_the_pipe_open() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch_dir();
+        let script = dir.join("leave-child");
+        std::fs::write(&script, "#!/bin/sh\necho contract-id: abc\nsleep 30 2>/dev/null &\nexit 0\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let cli = cli(script.to_str().unwrap(), &dir);
+        let run = cli.run(&["borrow", "init"], Duration::from_secs(10)).await;
+
+        assert!(run.succeeded(), "{}", run.stderr());
+        assert_eq!(run.stdout().trim(), "contract-id: abc");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+
+pub const LATEST_BUILD: &str = "latest";
+
+// A borrower CLI release that runs can target by label
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CliBuild {
+    #[serde(default)]
+    pub label: String,
+    pub url: String,
+    // Expected SHA-256 of the binary; unset for moving targets such as "latest"
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildsFile {
+    #[serde(default)]
+    builds: BTreeMap<String, CliBuild>,
+}
+
+fn builtin_builds() -> BTreeMap<String, CliBuild> {
+    let mut builds = BTreeMap::new();
+
+    builds.insert(LATEST_BUILD.to_string(), CliBuild {
+        label: LATEST_BUILD.to_string(),
+        url: "https://loans-borrower-cli.s3.amazonaws.com/loans-borrower-cli-linux".to_string(),
+        sha256: None,
+    });
+
+    builds
+}
+
+// Labels become part of child run ids, so keep them URL-safe
+fn validate_label(label: &str) -> Result<()> {
+    let valid = !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Invalid CLI build label '{}': use letters, digits, '.', '-' and '_'",
+            label
+        ))
+    }
+}
+
+// Built-in builds overlaid with the ones from CLI_BUILDS_FILE (TOML)
+pub fn load_builds() -> Result<BTreeMap<String, CliBuild>> {
+    let mut builds = builtin_builds();
+
+    if let Ok(path) = env::var("CLI_BUILDS_FILE") {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read CLI builds file {}", path))?;
+        let file: BuildsFile = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse CLI builds file {}", path))?;
+        add_builds(&mut builds, file)?;
+    }
+
+    Ok(builds)
+}
+
+// Adds the builds of a builds file, replacing built-in ones with the same label
+fn add_builds(builds: &mut BTreeMap<String, CliBuild>, file: BuildsFile) -> Result<()> {
+    for (label, mut build) in file.builds {
+        validate_label(&label)?;
+        build.label = label.clone();
+        builds.insert(label, build);
+    }
+
+    Ok(())
+}
+
+// Looks up builds by label, in the given order; duplicates are rejected
+pub fn resolve_builds(labels: &[String]) -> Result<Vec<CliBuild>> {
+    select_builds(&load_builds()?, labels)
+}
+
+fn select_builds(builds: &BTreeMap<String, CliBuild>, labels: &[String]) -> Result<Vec<CliBuild>> {
+    let mut resolved: Vec<CliBuild> = Vec::new();
+
+    for label in labels {
+        if resolved.iter().any(|build| &build.label == label) {
+            return Err(anyhow!("CLI build '{}' is listed more than once", label));
+        }
+        let build = builds.get(label).cloned().ok_or_else(|| {
+            anyhow!(
+                "Unknown CLI build '{}' (available: {})",
+                label,
+                builds.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        resolved.push(build);
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILDS: &str = r#"
+        [builds."v1.4.2"]
+        url = "https://example.com/loans-borrower-cli-v1.4.2"
+        sha256 = "4f2a9c0d"
+
+        [builds.latest]
+        url = "https://example.com/loans-borrower-cli-nightly"
+    "#;
+
+    fn builds() -> BTreeMap<String, CliBuild> {
+        let mut builds = builtin_builds();
+        add_builds(&mut builds, toml::from_str(BUILDS).unwrap()).unwrap();
+        builds
+    }
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    fn builds_file_adds_and_overrides_builds() {
+        let builds = builds();
+
+        let pinned = &builds["v1.4.2"];
+        assert_eq!(pinned.label, "v1.4.2");
+        assert_eq!(pinned.url, "https://example.com/loans-borrower-cli-v1.4.2");
+        assert_eq!(pinned.sha256.as_deref(), Some("4f2a9c0d"));
+
+        let latest = &builds[LATEST_BUILD];
+        assert_eq!(latest.url, "https://example.com/loans-borrower-cli-nightly");
+        assert_eq!(latest.sha256, None);
+    }
+
+    #[test]
+    fn builds_are_resolved_in_the_requested_order() {
+        let resolved = select_builds(&builds(), &labels(&["latest", "v1.4.2"])).unwrap();
+        let resolved: Vec<_> = resolved.iter().map(|build| build.label.as_str()).collect();
+        assert_eq!(resolved, ["latest", "v1.4.2"]);
+    }
+
+    #[test]
+    fn unknown_builds_are_rejected() {
+        let error = select_builds(&builds(), &labels(&["v9"])).unwrap_err().to_string();
+        assert_eq!(error, "Unknown CLI build 'v9' (available: latest, v1.4.2)");
+    }
+
+    #[test]
+    fn duplicate_builds_are_rejected() {
+        let error = select_builds(&builds(), &labels(&["v1.4.2", "v1.4.2"])).unwrap_err().to_string();
+        assert!(error.contains("listed more than once"), "{}", error);
+    }
+
+    #[test]
+    fn labels_must_be_url_safe() {
+        let mut builds = builtin_builds();
+        let file = toml::from_str(r#"
+            [builds."v1/evil"]
+            url = "https://example.com/cli"
+        "#)
+        .unwrap();
+        let error = add_builds(&mut builds, file).unwrap_err().to_string();
+        assert!(error.contains("Invalid CLI build label 'v1/evil'"), "{}", error);
+    }
+
+    #[test]
+    fn builds_need_a_url() {
+        assert!(toml::from_str::<BuildsFile>("[builds.broken]\nsha256 = \"00\"\n").is_err());
+    }
+}
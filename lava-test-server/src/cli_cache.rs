@@ -0,0 +1,488 @@
+use log::{info, warn};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::timeout;
+use uuid::Uuid;
+
+const CLI_FILE_NAME: &str = "loans-borrower-cli";
+
+// `--version` should answer immediately; anything slower is treated as broken
+const VERSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Why installing the borrower CLI failed
+#[derive(Debug)]
+pub enum CliInstallError {
+    Download(String),
+    Truncated { expected: u64, actual: u64 },
+    ChecksumMismatch { expected: String, actual: String },
+    NotExecutable(String),
+    Io(String),
+}
+
+impl CliInstallError {
+    // Stable identifier for the failure, recorded with the test result
+    pub fn class(&self) -> &'static str {
+        match self {
+            CliInstallError::Download(_) => "cli_download_failed",
+            CliInstallError::Truncated { .. } => "cli_download_truncated",
+            CliInstallError::ChecksumMismatch { .. } => "cli_checksum_mismatch",
+            CliInstallError::NotExecutable(_) => "cli_not_executable",
+            CliInstallError::Io(_) => "cli_cache_io",
+        }
+    }
+}
+
+impl fmt::Display for CliInstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliInstallError::Download(e) => write!(f, "Failed to download CLI: {}", e),
+            CliInstallError::Truncated { expected, actual } => write!(
+                f,
+                "CLI download truncated: expected {} bytes, got {}",
+                expected, actual
+            ),
+            CliInstallError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "CLI checksum mismatch: expected sha256 {}, got {}",
+                expected, actual
+            ),
+            CliInstallError::NotExecutable(e) => write!(f, "CLI binary cannot be executed: {}", e),
+            CliInstallError::Io(e) => write!(f, "CLI cache error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliInstallError {}
+
+fn io_error(context: &str, path: &Path, e: std::io::Error) -> CliInstallError {
+    CliInstallError::Io(format!("{} {}: {}", context, path.display(), e))
+}
+
+// A verified CLI binary ready to run
+#[derive(Debug, Clone)]
+pub struct InstalledCli {
+    pub path: PathBuf,
+    pub sha256: String,
+    pub version: Option<String>,
+}
+
+// What the cache knows about a download URL, for revalidation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IndexEntry {
+    sha256: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+// Content-addressed cache of CLI binaries, keyed by SHA-256
+pub struct CliCache {
+    root: PathBuf,
+}
+
+impl CliCache {
+    // Uses CLI_CACHE_DIR, or lava-cli/cache under the system temp directory
+    pub fn from_env() -> Self {
+        let root = env::var("CLI_CACHE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| env::temp_dir().join("lava-cli").join("cache"));
+        Self { root }
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.root.join("index.json")
+    }
+
+    // Objects keep the CLI's file name so the binary sees the expected argv[0]
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        self.root.join("objects").join(sha256).join(CLI_FILE_NAME)
+    }
+
+    fn load_index(&self) -> HashMap<String, IndexEntry> {
+        fs::read_to_string(self.index_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_index(&self, index: &HashMap<String, IndexEntry>) -> Result<(), CliInstallError> {
+        let path = self.index_path();
+        let tmp_path = path.with_extension(format!("json.{}.tmp", Uuid::new_v4()));
+        let content = serde_json::to_vec_pretty(index)
+            .map_err(|e| CliInstallError::Io(format!("Failed to serialize cache index: {}", e)))?;
+        fs::write(&tmp_path, content).map_err(|e| io_error("Failed to write", &tmp_path, e))?;
+        fs::rename(&tmp_path, &path).map_err(|e| io_error("Failed to replace", &path, e))
+    }
+
+    // Returns the cached object for the hash if it is present and intact
+    fn verified_object(&self, sha256: &str) -> Option<PathBuf> {
+        let path = self.object_path(sha256);
+        let content = fs::read(&path).ok()?;
+        if sha256_hex(&content) == sha256 {
+            Some(path)
+        } else {
+            warn!("Cached CLI {} is corrupt, discarding it", path.display());
+            let _ = fs::remove_file(&path);
+            None
+        }
+    }
+
+    fn store_object(&self, content: &[u8], sha256: &str) -> Result<PathBuf, CliInstallError> {
+        let path = self.object_path(sha256);
+        let dir = path.parent().expect("object path has a parent");
+        fs::create_dir_all(dir).map_err(|e| io_error("Failed to create", dir, e))?;
+
+        let tmp_path = dir.join(format!("{}.{}.tmp", CLI_FILE_NAME, Uuid::new_v4()));
+        let mut file = fs::File::create(&tmp_path).map_err(|e| io_error("Failed to create", &tmp_path, e))?;
+        file.write_all(content).map_err(|e| io_error("Failed to write", &tmp_path, e))?;
+
+        // Make the CLI executable
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o755))
+                .map_err(|e| io_error("Failed to set permissions on", &tmp_path, e))?;
+        }
+
+        fs::rename(&tmp_path, &path).map_err(|e| io_error("Failed to install", &path, e))?;
+        Ok(path)
+    }
+
+    // Fetches the CLI from the URL unless an intact cached copy is still current.
+    // With `pinned_sha256` the binary must have exactly that hash.
+    pub async fn install(&self, url: &str, pinned_sha256: Option<&str>) -> Result<InstalledCli, CliInstallError> {
+        let pinned_sha256 = pinned_sha256.map(|sha| sha.to_ascii_lowercase());
+        fs::create_dir_all(&self.root).map_err(|e| io_error("Failed to create", &self.root, e))?;
+
+        // A pinned hash that is already cached needs no network round trip
+        if let Some(pinned) = &pinned_sha256 {
+            if let Some(path) = self.verified_object(pinned) {
+                info!("Using pinned CLI {} from cache", pinned);
+                return finish_install(path, pinned.clone()).await;
+            }
+        }
+
+        let mut index = self.load_index();
+        let cached = index
+            .get(url)
+            .cloned()
+            .and_then(|entry| self.verified_object(&entry.sha256).map(|path| (entry, path)));
+
+        let mut request = Client::new().get(url);
+        if let Some((entry, _)) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        info!("Downloading CLI from {}", url);
+        let mut response = match request.send().await.and_then(|response| response.error_for_status()) {
+            Ok(response) => response,
+            Err(e) => match cached {
+                Some((entry, path)) if pinned_sha256.is_none() => {
+                    warn!("Could not revalidate cached CLI ({}), using cached copy", e);
+                    return finish_install(path, entry.sha256).await;
+                }
+                _ => return Err(CliInstallError::Download(e.to_string())),
+            },
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some((entry, path)) = cached {
+                info!("Cached CLI {} is still current", entry.sha256);
+                verify_pin(pinned_sha256.as_deref(), &entry.sha256)?;
+                return finish_install(path, entry.sha256).await;
+            }
+        }
+
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+        let expected_length = response.content_length();
+
+        // Read chunk by chunk so a connection dropped mid-body is reported as truncation
+        let mut content = Vec::new();
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => content.extend_from_slice(&chunk),
+                Ok(None) => break,
+                Err(e) => match expected_length {
+                    Some(expected) => {
+                        warn!("CLI download interrupted: {}", e);
+                        return Err(CliInstallError::Truncated {
+                            expected,
+                            actual: content.len() as u64,
+                        });
+                    }
+                    None => return Err(CliInstallError::Download(format!("Failed to read CLI content: {}", e))),
+                },
+            }
+        }
+
+        if let Some(expected) = expected_length {
+            if content.len() as u64 != expected {
+                return Err(CliInstallError::Truncated {
+                    expected,
+                    actual: content.len() as u64,
+                });
+            }
+        }
+        if content.is_empty() {
+            return Err(CliInstallError::Truncated { expected: expected_length.unwrap_or(0), actual: 0 });
+        }
+
+        let sha256 = sha256_hex(&content);
+        verify_pin(pinned_sha256.as_deref(), &sha256)?;
+
+        let path = self.store_object(&content, &sha256)?;
+        index.insert(url.to_string(), IndexEntry {
+            sha256: sha256.clone(),
+            etag,
+            last_modified,
+        });
+        self.save_index(&index)?;
+
+        info!("CLI {} cached at {}", sha256, path.display());
+        finish_install(path, sha256).await
+    }
+}
+
+fn verify_pin(pinned: Option<&str>, actual: &str) -> Result<(), CliInstallError> {
+    match pinned {
+        Some(expected) if expected != actual => Err(CliInstallError::ChecksumMismatch {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Runs `--version` to prove the binary executes on this host
+async fn finish_install(path: PathBuf, sha256: String) -> Result<InstalledCli, CliInstallError> {
+    let output = Command::new(&path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = match timeout(VERSION_TIMEOUT, output).await {
+        Ok(output) => output.map_err(|e| CliInstallError::NotExecutable(format!("{}: {}", path.display(), e)))?,
+        Err(_) => {
+            return Err(CliInstallError::NotExecutable(format!(
+                "{} --version did not exit within {}s",
+                path.display(),
+                VERSION_TIMEOUT.as_secs()
+            )))
+        }
+    };
+
+    let version = if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        warn!(
+            "CLI --version exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        None
+    };
+
+    info!("CLI ready: {} (sha256 {}, version {:?})", path.display(), sha256, version);
+    Ok(InstalledCli { path, sha256, version })
+}
+
+pub fn sha256_hex(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{Response, ScriptedServer};
+
+    const SCRIPT: &str = "#!/bin/sh\necho loans-borrower-cli 1.0.0\n";
+
+    fn cache() -> CliCache {
+        CliCache { root: env::temp_dir().join(format!("lava-cli-cache-test-{}", Uuid::new_v4())) }
+    }
+
+    fn cli_url(server: &ScriptedServer) -> String {
+        format!("{}/loans-borrower-cli", server.url)
+    }
+
+    #[tokio::test]
+    async fn downloads_caches_and_runs_the_cli() {
+        let cache = cache();
+        let server = ScriptedServer::sequence(vec![Response::status(200).header("ETag", "\"v1\"").body(SCRIPT)]).await;
+
+        let installed = cache.install(&cli_url(&server), None).await.unwrap();
+        assert_eq!(installed.sha256, sha256_hex(SCRIPT.as_bytes()));
+        assert_eq!(installed.version.as_deref(), Some("loans-borrower-cli 1.0.0"));
+        assert_eq!(installed.path, cache.object_path(&installed.sha256));
+        assert_eq!(cache.load_index()[&cli_url(&server)].etag.as_deref(), Some("\"v1\""));
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn not_modified_reuses_the_cached_copy() {
+        let cache = cache();
+        let server = ScriptedServer::sequence(vec![
+            Response::status(200).header("ETag", "\"v1\"").body(SCRIPT),
+            Response::status(304),
+        ]).await;
+
+        let first = cache.install(&cli_url(&server), None).await.unwrap();
+        let second = cache.install(&cli_url(&server), None).await.unwrap();
+        assert_eq!(second.sha256, first.sha256);
+        assert_eq!(second.path, first.path);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("If-None-Match"), None);
+        assert_eq!(requests[1].header("If-None-Match"), Some("\"v1\""));
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn changed_cli_replaces_the_cached_copy() {
+        let cache = cache();
+        let updated = "#!/bin/sh\necho loans-borrower-cli 1.1.0\n";
+        let server = ScriptedServer::sequence(vec![
+            Response::status(200).header("ETag", "\"v1\"").body(SCRIPT),
+            Response::status(200).header("ETag", "\"v2\"").body(updated),
+        ]).await;
+
+        cache.install(&cli_url(&server), None).await.unwrap();
+        let installed = cache.install(&cli_url(&server), None).await.unwrap();
+        assert_eq!(installed.sha256, sha256_hex(updated.as_bytes()));
+        assert_eq!(installed.version.as_deref(), Some("loans-borrower-cli 1.1.0"));
+        assert_eq!(cache.load_index()[&cli_url(&server)].etag.as_deref(), Some("\"v2\""));
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn unreachable_server_falls_back_to_the_cached_copy() {
+        let cache = cache();
+        let server = ScriptedServer::sequence(vec![
+            Response::status(200).header("ETag", "\"v1\"").body(SCRIPT),
+            Response::status(503),
+        ]).await;
+
+        let first = cache.install(&cli_url(&server), None).await.unwrap();
+        let second = cache.install(&cli_url(&server), None).await.unwrap();
+        assert_eq!(second.sha256, first.sha256);
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn corrupt_cached_copy_is_downloaded_again() {
+        let cache = cache();
+        let server = ScriptedServer::sequence(vec![
+            Response::status(200).header("ETag", "\"v1\"").body(SCRIPT),
+            Response::status(200).header("ETag", "\"v1\"").body(SCRIPT),
+        ]).await;
+
+        let first = cache.install(&cli_url(&server), None).await.unwrap();
+        fs::write(&first.path, "corrupted").unwrap();
+        let second = cache.install(&cli_url(&server), None).await.unwrap();
+        assert_eq!(second.sha256, first.sha256);
+        assert_eq!(fs::read_to_string(&second.path).unwrap(), SCRIPT);
+
+        // The corrupt copy must not be revalidated, so no ETag is sent
+        assert_eq!(server.requests()[1].header("If-None-Match"), None);
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn dropped_connection_is_reported_as_truncated() {
+        let cache = cache();
+        let server = ScriptedServer::sequence(vec![Response::status(200).body(SCRIPT).truncated(SCRIPT.len() + 100)]).await;
+
+        let err = cache.install(&cli_url(&server), None).await.unwrap_err();
+        assert_eq!(err.class(), "cli_download_truncated");
+        assert!(matches!(err, CliInstallError::Truncated { expected, .. } if expected == SCRIPT.len() as u64 + 100));
+        assert!(cache.load_index().is_empty());
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn empty_download_is_reported_as_truncated() {
+        let cache = cache();
+        let server = ScriptedServer::sequence(vec![Response::status(200)]).await;
+
+        let err = cache.install(&cli_url(&server), None).await.unwrap_err();
+        assert_eq!(err.class(), "cli_download_truncated");
+        assert!(cache.load_index().is_empty());
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn pinned_checksum_mismatch_is_refused() {
+        let cache = cache();
+        let pinned = sha256_hex(b"a different build");
+        let server = ScriptedServer::sequence(vec![Response::status(200).body(SCRIPT)]).await;
+
+        let err = cache.install(&cli_url(&server), Some(&pinned)).await.unwrap_err();
+        assert_eq!(err.class(), "cli_checksum_mismatch");
+        assert!(matches!(&err, CliInstallError::ChecksumMismatch { expected, .. } if *expected == pinned));
+        assert!(cache.load_index().is_empty());
+        assert!(!cache.object_path(&sha256_hex(SCRIPT.as_bytes())).exists());
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn pinned_checksum_mismatch_is_refused_on_not_modified() {
+        let cache = cache();
+        let pinned = sha256_hex(b"a different build");
+        let server = ScriptedServer::sequence(vec![
+            Response::status(200).header("ETag", "\"v1\"").body(SCRIPT),
+            Response::status(304),
+        ]).await;
+
+        cache.install(&cli_url(&server), None).await.unwrap();
+        let err = cache.install(&cli_url(&server), Some(&pinned)).await.unwrap_err();
+        assert_eq!(err.class(), "cli_checksum_mismatch");
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+
+    #[tokio::test]
+    async fn pinned_checksum_is_served_from_cache_without_a_request() {
+        let cache = cache();
+        let pinned = sha256_hex(SCRIPT.as_bytes()).to_ascii_uppercase();
+        let server = ScriptedServer::sequence(vec![Response::status(200).body(SCRIPT)]).await;
+
+        let first = cache.install(&cli_url(&server), Some(&pinned)).await.unwrap();
+        assert_eq!(first.sha256, pinned.to_ascii_lowercase());
+        let second = cache.install(&cli_url(&server), Some(&pinned)).await.unwrap();
+        assert_eq!(second.path, first.path);
+        assert_eq!(server.requests().len(), 1);
+
+        let _ = fs::remove_dir_all(&cache.root);
+    }
+}
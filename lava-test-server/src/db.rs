@@ -0,0 +1,865 @@
+use anyhow::{Context, Result, anyhow};
+use bip39::Mnemonic;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use sqlx::Row;
+use std::collections::{HashMap, HashSet};
+use std::env;
+
+use crate::{loan, queue, secrets, steps};
+use crate::{PendingPoll, RunJob, RunSettings, StepLog, TestResult, TestResultsFilter, STATUS_COMPLETED};
+
+// Outcome of adding a run to the queue
+pub enum Enqueued {
+    Position(u64),
+    Full,
+    Duplicate,
+}
+
+async fn ensure_schema(pool: &sqlx::SqlitePool) -> Result<()> {
+    // Create the table if it doesn't exist
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS test_results (
+            id TEXT PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            success INTEGER NOT NULL,
+            mnemonic TEXT NOT NULL,
+            btc_address TEXT NOT NULL,
+            lava_usd_pubkey TEXT NOT NULL,
+            contract_id TEXT,
+            collateral_repayment_txid TEXT,
+            error_message TEXT,
+            details TEXT
+        )"
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create table")?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS step_logs (
+            run_id TEXT NOT NULL,
+            step TEXT NOT NULL,
+            command TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            outcome TEXT NOT NULL,
+            exit_code INTEGER,
+            signal INTEGER,
+            duration_ms INTEGER NOT NULL,
+            timeout_secs INTEGER,
+            stdout TEXT NOT NULL,
+            stderr TEXT NOT NULL,
+            PRIMARY KEY (run_id, step)
+        )"
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create step_logs table")?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS test_steps (
+            run_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TEXT,
+            ended_at TEXT,
+            duration_ms INTEGER,
+            error_class TEXT,
+            error_message TEXT,
+            artifacts TEXT NOT NULL,
+            PRIMARY KEY (run_id, position)
+        )"
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create test_steps table")?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS pending_runs (
+            run_id TEXT PRIMARY KEY,
+            step_index INTEGER NOT NULL,
+            next_poll_at TEXT NOT NULL,
+            settings TEXT NOT NULL
+        )"
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create pending_runs table")?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS run_queue (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id TEXT NOT NULL UNIQUE,
+            job TEXT NOT NULL,
+            mnemonic TEXT,
+            queued_at TEXT NOT NULL,
+            started_at TEXT
+        )"
+    )
+    .execute(pool)
+    .await
+    .context("Failed to create run_queue table")?;
+
+    // Add columns introduced after the table was first created
+    let existing: Vec<String> = sqlx::query("PRAGMA table_info(test_results)")
+        .fetch_all(pool)
+        .await
+        .context("Failed to read table info")?
+        .iter()
+        .map(|row| row.get::<String, _>("name"))
+        .collect();
+
+    let added_columns = [
+        ("btc_derivation_path", "TEXT NOT NULL DEFAULT ''"),
+        ("rng_seed", "TEXT"),
+        ("mnemonic_source", "TEXT"),
+        ("profile", "TEXT"),
+        ("funding_txid", "TEXT"),
+        ("funding_block_height", "INTEGER"),
+        ("cli_sha256", "TEXT"),
+        ("cli_version", "TEXT"),
+        ("cli_build", "TEXT"),
+        ("parent_run_id", "TEXT"),
+        ("loan_capital_asset", "TEXT"),
+        ("ltv_ratio_bp", "INTEGER"),
+        ("loan_duration_days", "INTEGER"),
+        ("loan_amount", "REAL"),
+        ("scenario", "TEXT"),
+        ("suite", "TEXT"),
+        ("status", "TEXT"),
+        ("maturity_deadline", "TEXT"),
+        ("run_details", "TEXT"),
+    ];
+
+    for (name, definition) in added_columns {
+        if !existing.iter().any(|c| c == name) {
+            info!("Adding column {} to test_results", name);
+            sqlx::query(&format!("ALTER TABLE test_results ADD COLUMN {} {}", name, definition))
+                .execute(pool)
+                .await
+                .with_context(|| format!("Failed to add column {}", name))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn save_test_result_to_db(test_result: &TestResult) -> Result<()> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    // Never store the mnemonic in plaintext
+    let cipher = secrets::MnemonicCipher::from_env()?;
+    let encrypted_mnemonic = cipher.encrypt(&test_result.mnemonic)?;
+
+    // Serialize the details to JSON
+    let details_json = serde_json::to_string(&test_result.details)
+        .context("Failed to serialize details")?;
+    let run_details_json = serde_json::to_string(&test_result.run_details)
+        .context("Failed to serialize run details")?;
+
+    // Insert the test result, replacing the earlier state of a resumed run
+    sqlx::query(
+        "INSERT OR REPLACE INTO test_results 
+        (id, timestamp, success, mnemonic, btc_address, btc_derivation_path, lava_usd_pubkey, contract_id, collateral_repayment_txid, error_message, details, rng_seed, mnemonic_source, profile, funding_txid, funding_block_height, cli_sha256, cli_version, cli_build, parent_run_id, loan_capital_asset, ltv_ratio_bp, loan_duration_days, loan_amount, scenario, suite, status, maturity_deadline, run_details) 
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&test_result.id)
+    .bind(test_result.timestamp.to_rfc3339())
+    .bind(test_result.success as i32)
+    .bind(&encrypted_mnemonic)
+    .bind(&test_result.btc_address)
+    .bind(&test_result.btc_derivation_path)
+    .bind(&test_result.lava_usd_pubkey)
+    .bind(&test_result.contract_id)
+    .bind(&test_result.collateral_repayment_txid)
+    .bind(&test_result.error_message)
+    .bind(&details_json)
+    .bind(test_result.rng_seed.map(|seed| seed.to_string()))
+    .bind(&test_result.mnemonic_source)
+    .bind(&test_result.profile)
+    .bind(&test_result.funding_txid)
+    .bind(test_result.funding_block_height.map(|height| height as i64))
+    .bind(&test_result.cli_sha256)
+    .bind(&test_result.cli_version)
+    .bind(&test_result.cli_build)
+    .bind(&test_result.parent_run_id)
+    .bind(test_result.loan.as_ref().map(|loan| loan.capital_asset.clone()))
+    .bind(test_result.loan.as_ref().map(|loan| loan.ltv_ratio_bp))
+    .bind(test_result.loan.as_ref().map(|loan| loan.duration_days))
+    .bind(test_result.loan.as_ref().map(|loan| loan.amount))
+    .bind(&test_result.scenario)
+    .bind(&test_result.suite)
+    .bind(&test_result.status)
+    .bind(test_result.maturity_deadline.map(|deadline| deadline.to_rfc3339()))
+    .bind(&run_details_json)
+    .execute(&pool)
+    .await
+    .context("Failed to insert test result")?;
+
+    save_test_steps(&pool, &test_result.id, &test_result.steps).await?;
+
+    info!("Saved test result to database: {}", test_result.id);
+    pool.close().await;
+    Ok(())
+}
+
+async fn save_test_steps(pool: &sqlx::SqlitePool, run_id: &str, steps: &steps::Steps) -> Result<()> {
+    sqlx::query("DELETE FROM test_steps WHERE run_id = ?")
+        .bind(run_id)
+        .execute(pool)
+        .await
+        .context("Failed to clear test steps")?;
+
+    for (position, step) in steps.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO test_steps 
+            (run_id, position, name, status, started_at, ended_at, duration_ms, error_class, error_message, artifacts) 
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(run_id)
+        .bind(position as i64)
+        .bind(&step.name)
+        .bind(step.status.as_str())
+        .bind(step.started_at.map(|time| time.to_rfc3339()))
+        .bind(step.ended_at.map(|time| time.to_rfc3339()))
+        .bind(step.duration_ms.map(|ms| ms as i64))
+        .bind(&step.error_class)
+        .bind(&step.error_message)
+        .bind(serde_json::to_string(&step.artifacts).context("Failed to serialize step artifacts")?)
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to insert step {}", step.name))?;
+    }
+
+    Ok(())
+}
+
+fn test_step_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<steps::TestStep> {
+    let parse_time = |column: &str| -> Result<Option<DateTime<Utc>>> {
+        let value: Option<String> = row.try_get(column)?;
+        Ok(value
+            .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+            .map(|time| time.with_timezone(&Utc)))
+    };
+    let status: String = row.try_get("status")?;
+    let artifacts: String = row.try_get("artifacts")?;
+
+    Ok(steps::TestStep {
+        name: row.try_get("name")?,
+        status: steps::StepStatus::parse(&status).ok_or_else(|| anyhow!("Unknown step status '{}'", status))?,
+        started_at: parse_time("started_at")?,
+        ended_at: parse_time("ended_at")?,
+        duration_ms: row
+            .try_get::<Option<i64>, _>("duration_ms")?
+            .map(|ms| ms as u64),
+        error_class: row.try_get("error_class")?,
+        error_message: row.try_get("error_message")?,
+        artifacts: serde_json::from_str(&artifacts).unwrap_or_default(),
+    })
+}
+
+// Steps of every run, or of a single run when `run_id` is given
+async fn load_test_steps(
+    pool: &sqlx::SqlitePool,
+    run_id: Option<&str>,
+) -> Result<HashMap<String, steps::Steps>> {
+    let rows = match run_id {
+        Some(run_id) => sqlx::query("SELECT * FROM test_steps WHERE run_id = ? ORDER BY position")
+            .bind(run_id)
+            .fetch_all(pool)
+            .await,
+        None => sqlx::query("SELECT * FROM test_steps ORDER BY run_id, position")
+            .fetch_all(pool)
+            .await,
+    }
+    .context("Failed to retrieve test steps")?;
+
+    let mut steps: HashMap<String, steps::Steps> = HashMap::new();
+    for row in &rows {
+        let run_id: String = row.try_get("run_id")?;
+        steps.entry(run_id).or_default().0.push(test_step_from_row(row)?);
+    }
+    Ok(steps)
+}
+
+pub async fn save_step_log_to_db(step_log: &StepLog) -> Result<()> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    // A rerun of the same step replaces the earlier log
+    sqlx::query(
+        "INSERT OR REPLACE INTO step_logs 
+        (run_id, step, command, started_at, outcome, exit_code, signal, duration_ms, timeout_secs, stdout, stderr) 
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&step_log.run_id)
+    .bind(&step_log.step)
+    .bind(&step_log.command)
+    .bind(step_log.started_at.to_rfc3339())
+    .bind(&step_log.outcome)
+    .bind(step_log.exit_code)
+    .bind(step_log.signal)
+    .bind(step_log.duration_ms as i64)
+    .bind(step_log.timeout_secs.map(|secs| secs as i64))
+    .bind(&step_log.stdout)
+    .bind(&step_log.stderr)
+    .execute(&pool)
+    .await
+    .context("Failed to insert step log")?;
+
+    pool.close().await;
+    Ok(())
+}
+
+// Poll times are stored in a fixed-width UTC format so they compare as text
+fn poll_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+pub async fn save_pending_run(run_id: &str, pending_poll: &PendingPoll, settings: &RunSettings) -> Result<()> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO pending_runs 
+        (run_id, step_index, next_poll_at, settings) 
+        VALUES (?, ?, ?, ?)"
+    )
+    .bind(run_id)
+    .bind(pending_poll.step_index as i64)
+    .bind(poll_time(pending_poll.next_poll_at))
+    .bind(serde_json::to_string(settings).context("Failed to serialize run settings")?)
+    .execute(&pool)
+    .await
+    .context("Failed to save pending run")?;
+
+    pool.close().await;
+    Ok(())
+}
+
+// Ids of all runs paused until their loan matures
+pub async fn pending_run_ids() -> Result<HashSet<String>> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let run_ids = sqlx::query("SELECT run_id FROM pending_runs")
+        .fetch_all(&pool)
+        .await
+        .context("Failed to retrieve pending runs");
+    pool.close().await;
+
+    run_ids?.iter().map(|row| Ok(row.try_get("run_id")?)).collect()
+}
+
+pub async fn delete_pending_run(run_id: &str) -> Result<()> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    sqlx::query("DELETE FROM pending_runs WHERE run_id = ?")
+        .bind(run_id)
+        .execute(&pool)
+        .await
+        .context("Failed to delete pending run")?;
+
+    pool.close().await;
+    Ok(())
+}
+
+// Paused runs whose next poll is due at `now`, oldest first
+pub async fn load_due_pending_runs(now: DateTime<Utc>) -> Result<Vec<(String, PendingPoll, RunSettings)>> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let rows = sqlx::query("SELECT * FROM pending_runs WHERE next_poll_at <= ? ORDER BY next_poll_at")
+        .bind(poll_time(now))
+        .fetch_all(&pool)
+        .await
+        .context("Failed to retrieve pending runs");
+    pool.close().await;
+
+    let mut due = Vec::new();
+    for row in rows? {
+        let run_id: String = row.try_get("run_id")?;
+        let next_poll_at: String = row.try_get("next_poll_at")?;
+        let settings: String = row.try_get("settings")?;
+        let settings: RunSettings = match serde_json::from_str(&settings) {
+            Ok(settings) => settings,
+            Err(e) => {
+                error!("Skipping pending run {} with unreadable settings: {}", run_id, e);
+                continue;
+            }
+        };
+        let pending_poll = PendingPoll {
+            step_index: row.try_get::<i64, _>("step_index")? as usize,
+            next_poll_at: DateTime::parse_from_rfc3339(&next_poll_at)
+                .map(|time| time.with_timezone(&Utc))
+                .context("Invalid next poll time")?,
+        };
+        due.push((run_id, pending_poll, settings));
+    }
+    Ok(due)
+}
+
+// Adds a run to the end of the queue unless as many runs as `max_depth` are
+// already waiting, and stores its queued result
+pub async fn enqueue_run(run_id: &str, job: &RunJob, max_depth: usize) -> Result<Enqueued> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    // A run awaiting maturity is still in progress, though no longer queued
+    let existing = sqlx::query("SELECT run_id FROM run_queue WHERE run_id = ? UNION SELECT run_id FROM pending_runs WHERE run_id = ?")
+        .bind(run_id)
+        .bind(run_id)
+        .fetch_optional(&pool)
+        .await
+        .context("Failed to look up queued run")?;
+    if existing.is_some() {
+        pool.close().await;
+        return Ok(Enqueued::Duplicate);
+    }
+
+    let mnemonic = match &job.settings().mnemonic {
+        Some(mnemonic) => Some(secrets::MnemonicCipher::from_env()?.encrypt(&mnemonic.to_string())?),
+        None => None,
+    };
+
+    // Checking the depth and inserting in one statement keeps concurrent
+    // requests from overfilling the queue
+    let inserted = sqlx::query(
+        "INSERT INTO run_queue (run_id, job, mnemonic, queued_at) 
+        SELECT ?, ?, ?, ? 
+        WHERE (SELECT COUNT(*) FROM run_queue WHERE started_at IS NULL) < ?"
+    )
+    .bind(run_id)
+    .bind(serde_json::to_string(job).context("Failed to serialize queued run")?)
+    .bind(mnemonic)
+    .bind(Utc::now().to_rfc3339())
+    .bind(max_depth as i64)
+    .execute(&pool)
+    .await;
+    // A concurrent request with the same id got in after the lookup above
+    let inserted = match inserted {
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            pool.close().await;
+            return Ok(Enqueued::Duplicate);
+        },
+        inserted => inserted.context("Failed to queue run")?,
+    };
+    if inserted.rows_affected() == 0 {
+        pool.close().await;
+        return Ok(Enqueued::Full);
+    }
+
+    let position = queue_state_in(&pool, run_id).await?.and_then(|(_, position)| position).unwrap_or(1);
+    pool.close().await;
+
+    save_test_result_to_db(&job.queued_result(run_id)).await?;
+    Ok(Enqueued::Position(position))
+}
+
+// Marks the oldest waiting run as started and loads its job. The job is an
+// error when it cannot be decoded; the run is still claimed so it can be dropped.
+pub async fn claim_next_run() -> Result<Option<(String, Result<RunJob>)>> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let row = sqlx::query(
+        "UPDATE run_queue SET started_at = ? 
+        WHERE seq = (SELECT seq FROM run_queue WHERE started_at IS NULL ORDER BY seq LIMIT 1) 
+        RETURNING run_id, job, mnemonic"
+    )
+    .bind(Utc::now().to_rfc3339())
+    .fetch_optional(&pool)
+    .await
+    .context("Failed to claim queued run")?;
+    let row = match row {
+        Some(row) => row,
+        None => {
+            pool.close().await;
+            return Ok(None);
+        }
+    };
+
+    let run_id: String = row.try_get("run_id")?;
+    sqlx::query("UPDATE test_results SET status = ? WHERE id = ?")
+        .bind(queue::STATUS_RUNNING)
+        .bind(&run_id)
+        .execute(&pool)
+        .await
+        .context("Failed to mark run as running")?;
+    pool.close().await;
+
+    let job: String = row.try_get("job")?;
+    let mnemonic: Option<String> = row.try_get("mnemonic")?;
+    let job = serde_json::from_str::<RunJob>(&job)
+        .context("Invalid queued run")
+        .and_then(|mut job| {
+            if let Some(stored) = mnemonic {
+                let phrase = secrets::MnemonicCipher::from_env()?.decrypt(&stored)?;
+                job.restore_mnemonic(Mnemonic::parse(phrase).context("Invalid queued mnemonic")?);
+            }
+            Ok(job)
+        });
+    Ok(Some((run_id, job)))
+}
+
+// Marks a run that is still queued or running as completed and failed, e.g.
+// when it panicked before storing its result
+pub async fn fail_unfinished_run(run_id: &str, class: &str, message: &str) -> Result<()> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let failure = serde_json::json!({ "class": class, "message": message });
+    sqlx::query(
+        "UPDATE test_results SET status = ?, success = 0, error_message = ?, 
+        run_details = json_set(CASE json_type(run_details) WHEN 'object' THEN run_details ELSE '{}' END, '$.failure', json(?)) 
+        WHERE id = ? AND status IN (?, ?)"
+    )
+    .bind(STATUS_COMPLETED)
+    .bind(format!("{}: {}", class, message))
+    .bind(failure.to_string())
+    .bind(run_id)
+    .bind(queue::STATUS_QUEUED)
+    .bind(queue::STATUS_RUNNING)
+    .execute(&pool)
+    .await
+    .context("Failed to store run failure")?;
+
+    pool.close().await;
+    Ok(())
+}
+
+pub async fn finish_queued_run(run_id: &str) -> Result<()> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    sqlx::query("DELETE FROM run_queue WHERE run_id = ?")
+        .bind(run_id)
+        .execute(&pool)
+        .await
+        .context("Failed to remove queued run")?;
+
+    pool.close().await;
+    Ok(())
+}
+
+// Puts runs that were started before a restart back in line, in their
+// original order; they run again from the start
+pub async fn requeue_interrupted_runs() -> Result<u64> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let requeued = sqlx::query("UPDATE run_queue SET started_at = NULL WHERE started_at IS NOT NULL")
+        .execute(&pool)
+        .await
+        .context("Failed to requeue interrupted runs")?
+        .rows_affected();
+    sqlx::query("UPDATE test_results SET status = ? WHERE id IN (SELECT run_id FROM run_queue)")
+        .bind(queue::STATUS_QUEUED)
+        .execute(&pool)
+        .await
+        .context("Failed to mark requeued runs as queued")?;
+
+    pool.close().await;
+    Ok(requeued)
+}
+
+// Whether a run in the queue has started, and its position while it waits
+pub async fn queue_state(run_id: &str) -> Result<Option<(bool, Option<u64>)>> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let state = queue_state_in(&pool, run_id).await;
+    pool.close().await;
+    state
+}
+
+async fn queue_state_in(pool: &sqlx::SqlitePool, run_id: &str) -> Result<Option<(bool, Option<u64>)>> {
+    let row = sqlx::query(
+        "SELECT started_at IS NOT NULL AS started, 
+        (SELECT COUNT(*) FROM run_queue AS ahead WHERE ahead.started_at IS NULL AND ahead.seq <= run_queue.seq) AS position 
+        FROM run_queue WHERE run_id = ?"
+    )
+    .bind(run_id)
+    .fetch_optional(pool)
+    .await
+    .context("Failed to look up queued run")?;
+
+    match row {
+        Some(row) => {
+            let started: bool = row.try_get("started")?;
+            let position: i64 = row.try_get("position")?;
+            Ok(Some((started, if started { None } else { Some(position as u64) })))
+        },
+        None => Ok(None),
+    }
+}
+
+pub async fn get_step_log_from_db(run_id: &str, step: &str) -> Result<Option<StepLog>> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let row = sqlx::query("SELECT * FROM step_logs WHERE run_id = ? AND step = ?")
+        .bind(run_id)
+        .bind(step)
+        .fetch_optional(&pool)
+        .await
+        .context("Failed to retrieve step log");
+    pool.close().await;
+
+    let row = match row? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let started_at: String = row.try_get("started_at")?;
+
+    Ok(Some(StepLog {
+        run_id: row.try_get("run_id")?,
+        step: row.try_get("step")?,
+        command: row.try_get("command")?,
+        started_at: DateTime::parse_from_rfc3339(&started_at)
+            .map(|dt| dt.with_timezone(&Utc))
+            .context("Invalid step start time")?,
+        outcome: row.try_get("outcome")?,
+        exit_code: row.try_get("exit_code")?,
+        signal: row.try_get("signal")?,
+        duration_ms: row.try_get::<i64, _>("duration_ms")? as u64,
+        timeout_secs: row
+            .try_get::<Option<i64>, _>("timeout_secs")?
+            .map(|secs| secs as u64),
+        stdout: row.try_get("stdout")?,
+        stderr: row.try_get("stderr")?,
+    }))
+}
+
+pub async fn encrypt_plaintext_mnemonics(pool: &sqlx::SqlitePool) -> Result<usize> {
+    ensure_schema(pool).await?;
+
+    let rows = sqlx::query("SELECT id, mnemonic FROM test_results WHERE mnemonic NOT LIKE 'enc:%'")
+        .fetch_all(pool)
+        .await
+        .context("Failed to query plaintext mnemonics")?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let cipher = secrets::MnemonicCipher::from_env()?;
+    for row in &rows {
+        let id: String = row.try_get("id")?;
+        let mnemonic: String = row.try_get("mnemonic")?;
+
+        sqlx::query("UPDATE test_results SET mnemonic = ? WHERE id = ?")
+            .bind(cipher.encrypt(&mnemonic)?)
+            .bind(&id)
+            .execute(pool)
+            .await
+            .with_context(|| format!("Failed to encrypt mnemonic for {}", id))?;
+    }
+
+    Ok(rows.len())
+}
+
+fn test_result_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<TestResult> {
+    let details_str: Option<String> = row.try_get("details")?;
+    let details: serde_json::Value = details_str
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or(serde_json::Value::Null);
+    let run_details_str: Option<String> = row.try_get("run_details")?;
+    let run_details: serde_json::Value = run_details_str
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or(serde_json::Value::Null);
+
+    let timestamp_str: String = row.try_get("timestamp")?;
+    let timestamp = match DateTime::parse_from_rfc3339(&timestamp_str) {
+        Ok(dt) => dt.with_timezone(&Utc),
+        Err(_) => Utc::now(),
+    };
+
+    // Seeds are stored as text since they do not fit in SQLite's signed integers
+    let rng_seed: Option<String> = row.try_get("rng_seed")?;
+
+    let maturity_deadline: Option<String> = row.try_get("maturity_deadline")?;
+
+    // Results stored before loan terms were configurable have no loan columns set
+    let loan_capital_asset: Option<String> = row.try_get("loan_capital_asset")?;
+    let loan = match loan_capital_asset {
+        Some(capital_asset) => Some(loan::LoanParams {
+            capital_asset,
+            ltv_ratio_bp: row.try_get::<Option<i64>, _>("ltv_ratio_bp")?.unwrap_or_default() as u32,
+            duration_days: row.try_get::<Option<i64>, _>("loan_duration_days")?.unwrap_or_default() as u32,
+            amount: row.try_get::<Option<f64>, _>("loan_amount")?.unwrap_or_default(),
+        }),
+        None => None,
+    };
+
+    Ok(TestResult {
+        id: row.try_get("id")?,
+        timestamp,
+        success: row.try_get::<i32, _>("success")? != 0,
+        mnemonic: row.try_get("mnemonic")?,
+        btc_address: row.try_get("btc_address")?,
+        btc_derivation_path: row.try_get("btc_derivation_path")?,
+        lava_usd_pubkey: row.try_get("lava_usd_pubkey")?,
+        contract_id: row.try_get("contract_id")?,
+        collateral_repayment_txid: row.try_get("collateral_repayment_txid")?,
+        error_message: row.try_get("error_message")?,
+        details,
+        run_details,
+        rng_seed: rng_seed.and_then(|seed| seed.parse().ok()),
+        mnemonic_source: row.try_get("mnemonic_source")?,
+        profile: row.try_get("profile")?,
+        funding_txid: row.try_get("funding_txid")?,
+        funding_block_height: row
+            .try_get::<Option<i64>, _>("funding_block_height")?
+            .map(|height| height as u64),
+        cli_sha256: row.try_get("cli_sha256")?,
+        cli_version: row.try_get("cli_version")?,
+        cli_build: row.try_get("cli_build")?,
+        parent_run_id: row.try_get("parent_run_id")?,
+        loan,
+        scenario: row.try_get("scenario")?,
+        suite: row.try_get("suite")?,
+        status: row
+            .try_get::<Option<String>, _>("status")?
+            .unwrap_or_else(|| STATUS_COMPLETED.to_string()),
+        maturity_deadline: maturity_deadline
+            .and_then(|deadline| DateTime::parse_from_rfc3339(&deadline).ok())
+            .map(|deadline| deadline.with_timezone(&Utc)),
+        steps: steps::Steps::default(),
+        queue_position: None,
+        pending_poll: None,
+    })
+}
+
+pub async fn get_test_result_from_db(run_id: &str) -> Option<TestResult> {
+    match env::var("DATABASE_URL") {
+        Ok(db_url) => {
+            match sqlx::SqlitePool::connect(&db_url).await {
+                Ok(pool) => {
+                    if let Err(e) = ensure_schema(&pool).await {
+                        error!("Failed to prepare database schema: {}", e);
+                        pool.close().await;
+                        return None;
+                    }
+
+                    let result = sqlx::query("SELECT * FROM test_results WHERE id = ?")
+                        .bind(run_id)
+                        .fetch_optional(&pool)
+                        .await;
+
+                    let test_result = match result {
+                        Ok(Some(row)) => match test_result_from_row(&row) {
+                            Ok(mut test_result) => {
+                                match load_test_steps(&pool, Some(run_id)).await {
+                                    Ok(mut steps) => test_result.steps = steps.remove(run_id).unwrap_or_default(),
+                                    Err(e) => error!("Failed to load steps of {}: {}", run_id, e),
+                                }
+                                Some(test_result)
+                            },
+                            Err(e) => {
+                                error!("Failed to decode test result {}: {}", run_id, e);
+                                None
+                            }
+                        },
+                        _ => None,
+                    };
+
+                    pool.close().await;
+                    test_result
+                },
+                Err(e) => {
+                    error!("Failed to connect to database: {}", e);
+                    None
+                }
+            }
+        },
+        Err(e) => {
+            error!("DATABASE_URL not set: {}", e);
+            None
+        }
+    }
+}
+
+pub async fn get_all_test_results_from_db(filter: &TestResultsFilter) -> Result<Vec<TestResult>> {
+    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
+    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
+
+    ensure_schema(&pool).await?;
+
+    let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new("SELECT * FROM test_results WHERE 1 = 1");
+    if let Some(capital_asset) = &filter.loan_capital_asset {
+        query.push(" AND loan_capital_asset = ").push_bind(capital_asset);
+    }
+    if let Some(ltv_ratio_bp) = filter.ltv_ratio_bp {
+        query.push(" AND ltv_ratio_bp = ").push_bind(ltv_ratio_bp);
+    }
+    if let Some(duration_days) = filter.loan_duration_days {
+        query.push(" AND loan_duration_days = ").push_bind(duration_days);
+    }
+    if let Some(amount) = filter.loan_amount {
+        query.push(" AND loan_amount = ").push_bind(amount);
+    }
+    if let Some(scenario) = &filter.scenario {
+        query.push(" AND scenario = ").push_bind(scenario);
+    }
+    if let Some(suite) = &filter.suite {
+        query.push(" AND suite = ").push_bind(suite);
+    }
+    query.push(" ORDER BY timestamp DESC");
+
+    let result = query.build().fetch_all(&pool).await;
+
+    match result {
+        Ok(rows) => {
+            let test_results = rows
+                .iter()
+                .map(test_result_from_row)
+                .collect::<Result<Vec<_>>>();
+            let steps = load_test_steps(&pool, None).await;
+
+            pool.close().await;
+            let mut steps = steps?;
+            test_results.map(|results| {
+                results
+                    .into_iter()
+                    .map(|mut result| {
+                        result.steps = steps.remove(&result.id).unwrap_or_default();
+                        result
+                    })
+                    .collect()
+            })
+        },
+        Err(e) => {
+            pool.close().await;
+            Err(anyhow!("Failed to retrieve test results: {}", e))
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use log::warn;
+use rand::Rng;
+use reqwest::{Client, Response, StatusCode};
+use serde::Serialize;
+use std::env;
+use std::time::Duration;
+use tokio::time::sleep;
+
+// How many times and how patiently faucet calls are retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    // Cap on the exponential backoff; a longer Retry-After is still honored
+    pub max_delay: Duration,
+    // Total time a call may spend waiting between attempts
+    pub deadline: Duration,
+}
+
+impl RetryPolicy {
+    // Reads FAUCET_MAX_ATTEMPTS, FAUCET_BASE_DELAY_MS, FAUCET_MAX_DELAY_MS and
+    // FAUCET_RETRY_DEADLINE_MS
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: u64| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_attempts: read("FAUCET_MAX_ATTEMPTS", 5).max(1) as u32,
+            base_delay: Duration::from_millis(read("FAUCET_BASE_DELAY_MS", 1000)),
+            max_delay: Duration::from_millis(read("FAUCET_MAX_DELAY_MS", 30000)),
+            deadline: Duration::from_millis(read("FAUCET_RETRY_DEADLINE_MS", 300000)),
+        }
+    }
+
+    // Exponential backoff with full jitter for the given (1-based) attempt
+    fn backoff(&self, attempt: u32, rng: &mut impl Rng) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        Duration::from_millis(rng.gen_range(0..=exponential.as_millis() as u64))
+    }
+}
+
+// One request to a faucet, as recorded in the run details
+#[derive(Debug, Clone, Serialize)]
+pub struct FaucetAttempt {
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub retry_after_ms: Option<u64>,
+    // Time waited before the next attempt; None for the last attempt
+    pub delay_ms: Option<u64>,
+}
+
+pub struct FaucetClient {
+    client: Client,
+    policy: RetryPolicy,
+}
+
+impl FaucetClient {
+    pub fn new(client: Client, policy: RetryPolicy) -> Self {
+        Self { client, policy }
+    }
+
+    // Posts the body to the faucet, retrying transient failures (connection errors,
+    // 408, 429 and 5xx). Returns every attempt made, whether or not the call succeeded.
+    pub async fn post<T: Serialize>(
+        &self,
+        name: &str,
+        url: &str,
+        body: &T,
+        rng: &mut impl Rng,
+    ) -> (Result<()>, Vec<FaucetAttempt>) {
+        let mut attempts = Vec::new();
+        let mut waited = Duration::ZERO;
+
+        for attempt in 1..=self.policy.max_attempts {
+            let started_at = Utc::now();
+            let started = std::time::Instant::now();
+            let response = self.client.post(url).json(body).send().await;
+
+            let mut record = FaucetAttempt {
+                attempt,
+                started_at,
+                duration_ms: started.elapsed().as_millis() as u64,
+                status: None,
+                error: None,
+                retry_after_ms: None,
+                delay_ms: None,
+            };
+
+            let retryable = match &response {
+                Ok(response) => {
+                    let status = response.status();
+                    record.status = Some(status.as_u16());
+                    if status.is_success() {
+                        attempts.push(record);
+                        return (Ok(()), attempts);
+                    }
+                    record.error = Some(format!("{} faucet request failed with status: {}", name, status));
+                    record.retry_after_ms = retry_after(response).map(|delay| delay.as_millis() as u64);
+                    is_retryable_status(status)
+                }
+                Err(e) => {
+                    record.error = Some(format!("{} faucet request error: {}", name, e));
+                    true
+                }
+            };
+
+            let error = record.error.clone().unwrap_or_default();
+            if !retryable {
+                attempts.push(record);
+                return (Err(anyhow!(error)), attempts);
+            }
+            if attempt == self.policy.max_attempts {
+                attempts.push(record);
+                return (Err(anyhow!("{} (gave up after {} attempts)", error, attempt)), attempts);
+            }
+
+            let backoff = self.policy.backoff(attempt, rng);
+            let delay = match record.retry_after_ms {
+                Some(retry_after_ms) => backoff.max(Duration::from_millis(retry_after_ms)),
+                None => backoff,
+            };
+            if waited + delay > self.policy.deadline {
+                let error = format!(
+                    "{} (waiting another {}ms would exceed the {}ms retry deadline)",
+                    error,
+                    delay.as_millis(),
+                    self.policy.deadline.as_millis()
+                );
+                attempts.push(record);
+                return (Err(anyhow!(error)), attempts);
+            }
+
+            warn!(
+                "{} (attempt {}/{}), retrying in {}ms",
+                error,
+                attempt,
+                self.policy.max_attempts,
+                delay.as_millis()
+            );
+            record.delay_ms = Some(delay.as_millis() as u64);
+            attempts.push(record);
+            waited += delay;
+            sleep(delay).await;
+        }
+
+        (Err(anyhow!("{} faucet request was never attempted", name)), attempts)
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+// Parses Retry-After as either delay-seconds or an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&Utc) - Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{Response, ScriptedServer};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use serde_json::json;
+
+    fn client(max_attempts: u32, max_delay_ms: u64, deadline_ms: u64) -> FaucetClient {
+        FaucetClient::new(Client::new(), RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(max_delay_ms),
+            deadline: Duration::from_millis(deadline_ms),
+        })
+    }
+
+    async fn post(client: &FaucetClient, server: &ScriptedServer) -> (Result<()>, Vec<FaucetAttempt>) {
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        client
+            .post("BTC", &format!("{}/mint-mutinynet", server.url), &json!({ "sats": 1000 }), &mut rng)
+            .await
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let server = ScriptedServer::sequence(vec![
+            Response::status(503),
+            Response::status(500),
+            Response::json(200, json!({ "txid": "abc" })),
+        ])
+        .await;
+
+        let (result, attempts) = post(&client(5, 10, 1000), &server).await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        let statuses: Vec<_> = attempts.iter().map(|attempt| attempt.status).collect();
+        assert_eq!(statuses, [Some(503), Some(500), Some(200)]);
+        assert!(attempts[..2].iter().all(|attempt| attempt.delay_ms.is_some()));
+        assert_eq!(attempts[2].delay_ms, None);
+        assert_eq!(server.requests().len(), 3);
+        let request = &server.requests()[0];
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/mint-mutinynet"));
+        assert_eq!(request.json()["sats"], 1000);
+    }
+
+    #[tokio::test]
+    async fn honors_retry_after_beyond_the_backoff_cap() {
+        let server = ScriptedServer::sequence(vec![
+            Response::status(429).header("Retry-After", "1"),
+            Response::json(200, json!({ "txid": "abc" })),
+        ])
+        .await;
+
+        let started = std::time::Instant::now();
+        let (result, attempts) = post(&client(5, 10, 5000), &server).await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(attempts[0].retry_after_ms, Some(1000));
+        assert_eq!(attempts[0].delay_ms, Some(1000));
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_retry_after_passes_the_deadline() {
+        let server = ScriptedServer::sequence(vec![
+            Response::status(429).header("Retry-After", "60"),
+            Response::json(200, json!({ "txid": "abc" })),
+        ])
+        .await;
+
+        let (result, attempts) = post(&client(5, 10, 5000), &server).await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("429"), "{}", error);
+        assert!(error.contains("retry deadline"), "{}", error);
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let server = ScriptedServer::sequence(vec![Response::status(502)]).await;
+
+        let (result, attempts) = post(&client(3, 10, 1000), &server).await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("gave up after 3 attempts"), "{}", error);
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(attempts[2].delay_ms, None);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = ScriptedServer::sequence(vec![
+            Response::status(400),
+            Response::json(200, json!({ "txid": "abc" })),
+        ])
+        .await;
+
+        let (result, attempts) = post(&client(5, 10, 1000), &server).await;
+
+        assert!(result.unwrap_err().to_string().contains("400"));
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_connection_errors() {
+        // Nothing listens on the port once the listener is dropped
+        let url = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/mint-mutinynet", listener.local_addr().unwrap())
+        };
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+
+        let (result, attempts) = client(2, 10, 1000).post("BTC", &url, &json!({}), &mut rng).await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts.iter().all(|attempt| attempt.status.is_none() && attempt.error.is_some()));
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            deadline: Duration::from_secs(60),
+        };
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        for (attempt, cap) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (9, 1000)] {
+            for _ in 0..50 {
+                assert!(policy.backoff(attempt, &mut rng) <= Duration::from_millis(cap));
+            }
+        }
+    }
+}
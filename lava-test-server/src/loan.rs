@@ -0,0 +1,197 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+// Loan assets the borrower CLI can borrow
+pub const CAPITAL_ASSETS: &[&str] = &["solana-lava-usd"];
+
+// Accepted ranges. These only keep out values that cannot be a loan; edge
+// values inside them are passed through so the CLI and backend get exercised.
+pub const MIN_LTV_RATIO_BP: u32 = 1;
+pub const MAX_LTV_RATIO_BP: u32 = 9_999;
+pub const MIN_DURATION_DAYS: u32 = 1;
+pub const MAX_DURATION_DAYS: u32 = 3_650;
+pub const MAX_LOAN_AMOUNT: f64 = 1_000_000_000.0;
+
+// Terms a run asks `borrow init` for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoanParams {
+    pub capital_asset: String,
+    pub ltv_ratio_bp: u32,
+    pub duration_days: u32,
+    pub amount: f64,
+}
+
+impl Default for LoanParams {
+    fn default() -> Self {
+        Self {
+            capital_asset: "solana-lava-usd".to_string(),
+            ltv_ratio_bp: 5_000,
+            duration_days: 4,
+            amount: 2.0,
+        }
+    }
+}
+
+impl LoanParams {
+    // Default terms overridden by whichever values the request supplies
+    pub fn resolve(
+        capital_asset: Option<&str>,
+        ltv_ratio_bp: Option<u32>,
+        duration_days: Option<u32>,
+        amount: Option<f64>,
+    ) -> Result<Self> {
+        let params = Self::unchecked(capital_asset, ltv_ratio_bp, duration_days, amount);
+        params.validate()?;
+        Ok(params)
+    }
+
+    // Like `resolve`, but lets through terms no loan can have, for scenarios
+    // that check the CLI refuses them
+    pub fn unchecked(
+        capital_asset: Option<&str>,
+        ltv_ratio_bp: Option<u32>,
+        duration_days: Option<u32>,
+        amount: Option<f64>,
+    ) -> Self {
+        let defaults = Self::default();
+        Self {
+            capital_asset: capital_asset.map_or(defaults.capital_asset, str::to_string),
+            ltv_ratio_bp: ltv_ratio_bp.unwrap_or(defaults.ltv_ratio_bp),
+            duration_days: duration_days.unwrap_or(defaults.duration_days),
+            amount: amount.unwrap_or(defaults.amount),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !CAPITAL_ASSETS.contains(&self.capital_asset.as_str()) {
+            return Err(anyhow!(
+                "Unknown loan capital asset '{}' (available: {})",
+                self.capital_asset,
+                CAPITAL_ASSETS.join(", ")
+            ));
+        }
+        if !(MIN_LTV_RATIO_BP..=MAX_LTV_RATIO_BP).contains(&self.ltv_ratio_bp) {
+            return Err(anyhow!(
+                "ltv_ratio_bp must be between {} and {}, got {}",
+                MIN_LTV_RATIO_BP,
+                MAX_LTV_RATIO_BP,
+                self.ltv_ratio_bp
+            ));
+        }
+        if !(MIN_DURATION_DAYS..=MAX_DURATION_DAYS).contains(&self.duration_days) {
+            return Err(anyhow!(
+                "loan_duration_days must be between {} and {}, got {}",
+                MIN_DURATION_DAYS,
+                MAX_DURATION_DAYS,
+                self.duration_days
+            ));
+        }
+        if !self.amount.is_finite() || self.amount <= 0.0 || self.amount > MAX_LOAN_AMOUNT {
+            return Err(anyhow!(
+                "loan_amount must be greater than 0 and at most {}, got {}",
+                MAX_LOAN_AMOUNT,
+                self.amount
+            ));
+        }
+        Ok(())
+    }
+
+    // `borrow init` flags for these terms
+    pub fn cli_args(&self) -> Vec<String> {
+        vec![
+            "--loan-capital-asset".to_string(),
+            self.capital_asset.clone(),
+            "--ltv-ratio-bp".to_string(),
+            self.ltv_ratio_bp.to_string(),
+            "--loan-duration-days".to_string(),
+            self.duration_days.to_string(),
+            "--loan-amount".to_string(),
+            self.amount.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ltv(ltv_ratio_bp: u32) -> Result<LoanParams> {
+        LoanParams::resolve(None, Some(ltv_ratio_bp), None, None)
+    }
+
+    fn duration(duration_days: u32) -> Result<LoanParams> {
+        LoanParams::resolve(None, None, Some(duration_days), None)
+    }
+
+    fn amount(amount: f64) -> Result<LoanParams> {
+        LoanParams::resolve(None, None, None, Some(amount))
+    }
+
+    #[test]
+    fn missing_values_fall_back_to_the_defaults() {
+        assert_eq!(LoanParams::resolve(None, None, None, None).unwrap(), LoanParams::default());
+
+        let params = LoanParams::resolve(Some("solana-lava-usd"), Some(9_500), Some(1), Some(0.5)).unwrap();
+        assert_eq!((params.ltv_ratio_bp, params.duration_days, params.amount), (9_500, 1, 0.5));
+    }
+
+    #[test]
+    fn ltv_edges_are_accepted_and_values_outside_refused() {
+        assert!(ltv(1).is_ok());
+        assert!(ltv(9_999).is_ok());
+
+        for value in [0, 10_000] {
+            let error = ltv(value).unwrap_err().to_string();
+            assert!(error.contains("ltv_ratio_bp must be between 1 and 9999"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn duration_edges_are_accepted_and_values_outside_refused() {
+        assert!(duration(1).is_ok());
+        assert!(duration(3_650).is_ok());
+
+        for value in [0, 3_651] {
+            let error = duration(value).unwrap_err().to_string();
+            assert!(error.contains("loan_duration_days must be between 1 and 3650"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn amount_must_be_positive_finite_and_at_most_the_maximum() {
+        assert!(amount(f64::MIN_POSITIVE).is_ok());
+        assert!(amount(MAX_LOAN_AMOUNT).is_ok());
+
+        for value in [0.0, -1.0, 1_000_000_001.0, f64::NAN, f64::INFINITY] {
+            let error = amount(value).unwrap_err().to_string();
+            assert!(error.contains("loan_amount must be greater than 0"), "{}: {}", value, error);
+        }
+    }
+
+    #[test]
+    fn unknown_capital_assets_are_refused() {
+        let error = LoanParams::resolve(Some("btc"), None, None, None).unwrap_err().to_string();
+        assert!(error.contains("Unknown loan capital asset 'btc'"), "{}", error);
+    }
+
+    #[test]
+    fn unchecked_terms_skip_validation() {
+        let params = LoanParams::unchecked(Some("btc"), Some(10_000), Some(0), Some(f64::NAN));
+        assert_eq!((params.ltv_ratio_bp, params.duration_days), (10_000, 0));
+        assert!(params.amount.is_nan());
+    }
+
+    #[test]
+    fn cli_args_carry_the_terms() {
+        let params = LoanParams::resolve(None, Some(9_999), Some(3_650), Some(2.5)).unwrap();
+        assert_eq!(
+            params.cli_args(),
+            [
+                "--loan-capital-asset", "solana-lava-usd",
+                "--ltv-ratio-bp", "9999",
+                "--loan-duration-days", "3650",
+                "--loan-amount", "2.5",
+            ]
+        );
+    }
+}
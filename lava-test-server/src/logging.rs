@@ -0,0 +1,299 @@
+use bip39::Language;
+use log::{Log, Metadata, Record};
+use regex::Regex;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{OnceLock, RwLock};
+
+// Shortest run of BIP39 words that is treated as a mnemonic
+const MIN_MNEMONIC_WORDS: usize = 12;
+
+// Environment variables whose values must never appear in logs
+const SECRET_ENV_VARS: &[&str] = &["ADMIN_TOKEN", "MNEMONIC_KEY"];
+
+const MASK: &str = "[REDACTED]";
+
+// Registered secret values with the number of live registrations for each
+fn secret_values() -> &'static RwLock<HashMap<String, usize>> {
+    static VALUES: OnceLock<RwLock<HashMap<String, usize>>> = OnceLock::new();
+    VALUES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn key_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // Extended private keys (BIP32 and SLIP-0132 variants)
+            r"\b[xtyzuv]prv[1-9A-HJ-NP-Za-km-z]{100,112}\b",
+            // WIF-encoded private keys
+            r"\b[5KLc9][1-9A-HJ-NP-Za-km-z]{50,51}\b",
+            // Solana keypair files: a JSON array of the 64 keypair bytes
+            r"\[\s*\d{1,3}(?:\s*,\s*\d{1,3}){63}\s*\]",
+        ]
+        .iter()
+        .map(|pattern| Regex::new(pattern).expect("valid redaction pattern"))
+        .collect()
+    })
+}
+
+// Base58 ed25519 keypairs (Solana secret keys) following a keypair name. Transaction
+// signatures have the same length and alphabet, so a bare 86-88 character string is kept.
+fn keypair_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"(?i)((?:keypair|secret[_ -]?key|private[_ -]?key)"?\s*[=:]?\s*"?)[1-9A-HJ-NP-Za-km-z]{86,88}\b"#)
+            .expect("valid redaction pattern")
+    })
+}
+
+// KEY=value and "key": "value" pairs naming a secret; the key itself is kept
+fn assignment_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"(?i)\b((?:mnemonic|seed|private[_-]?key|secret|password|token)"?\s*[=:]\s*)("[^"]*"|[^"\s,}]+)"#)
+            .expect("valid redaction pattern")
+    })
+}
+
+fn insert_secret(value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    if let Ok(mut values) = secret_values().write() {
+        *values.entry(value.to_string()).or_insert(0) += 1;
+    }
+}
+
+// Keeps a secret registered for redaction until dropped
+pub struct SecretGuard {
+    value: String,
+}
+
+impl Drop for SecretGuard {
+    fn drop(&mut self) {
+        if let Ok(mut values) = secret_values().write() {
+            if let Some(count) = values.get_mut(&self.value) {
+                *count -= 1;
+                if *count == 0 {
+                    values.remove(&self.value);
+                }
+            }
+        }
+    }
+}
+
+// Registers a value that must be masked in every log line
+pub fn register_secret(value: &str) -> SecretGuard {
+    insert_secret(value);
+    SecretGuard {
+        value: value.to_string(),
+    }
+}
+
+// Masks mnemonics, private keys and registered secret values in a message
+pub fn redact(message: &str) -> String {
+    let mut redacted = message.to_string();
+
+    if let Ok(values) = secret_values().read() {
+        for value in values.keys() {
+            redacted = redacted.replace(value.as_str(), MASK);
+        }
+    }
+
+    redacted = redact_mnemonic_words(&redacted);
+
+    for pattern in key_patterns() {
+        redacted = pattern.replace_all(&redacted, MASK).into_owned();
+    }
+    redacted = keypair_pattern().replace_all(&redacted, format!("${{1}}{}", MASK)).into_owned();
+
+    assignment_pattern()
+        .replace_all(&redacted, |caps: &regex::Captures| {
+            let quote = if caps[2].starts_with('"') { "\"" } else { "" };
+            format!("{}{}{}{}", &caps[1], quote, MASK, quote)
+        })
+        .into_owned()
+}
+
+// Replaces any run of MIN_MNEMONIC_WORDS or more whitespace-separated BIP39 words
+fn redact_mnemonic_words(message: &str) -> String {
+    static WORD: OnceLock<Regex> = OnceLock::new();
+    let word = WORD.get_or_init(|| Regex::new(r"[a-z]+").expect("valid word pattern"));
+
+    let mut runs = Vec::new();
+    let mut run: Option<(usize, usize, usize)> = None;
+
+    for m in word.find_iter(message) {
+        let is_bip39 = Language::English.find_word(m.as_str()).is_some();
+        run = match run {
+            Some((start, end, count))
+                if is_bip39 && message[end..m.start()].chars().all(char::is_whitespace) =>
+            {
+                Some((start, m.end(), count + 1))
+            }
+            _ => {
+                if let Some(finished) = run {
+                    runs.push(finished);
+                }
+                is_bip39.then_some((m.start(), m.end(), 1))
+            }
+        };
+    }
+    if let Some(finished) = run {
+        runs.push(finished);
+    }
+
+    let mut redacted = message.to_string();
+    for (start, end, count) in runs.into_iter().rev() {
+        if count >= MIN_MNEMONIC_WORDS {
+            redacted.replace_range(start..end, MASK);
+        }
+    }
+    redacted
+}
+
+// Wraps env_logger so every record is redacted before it is written
+struct RedactingLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for RedactingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+
+        let message = redact(&record.args().to_string());
+        self.inner.log(
+            &Record::builder()
+                .args(format_args!("{}", message))
+                .level(record.level())
+                .target(record.target())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+// Installs the redacting logger in place of a plain env_logger
+pub fn init() {
+    for name in SECRET_ENV_VARS {
+        if let Ok(value) = env::var(name) {
+            insert_secret(&value);
+        }
+    }
+
+    let inner = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).build();
+    let max_level = inner.filter();
+
+    log::set_boxed_logger(Box::new(RedactingLogger { inner }))
+        .expect("logger initialized only once");
+    log::set_max_level(max_level);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::bip32::Xpriv;
+    use bitcoin::{Network, PrivateKey};
+
+    const MNEMONIC: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    #[test]
+    fn masks_a_mnemonic_anywhere_in_a_message() {
+        assert_eq!(
+            redact(&format!("Using wallet {} for run 42", MNEMONIC)),
+            "Using wallet [REDACTED] for run 42"
+        );
+        assert_eq!(redact_mnemonic_words(&MNEMONIC.replace(' ', "\n  ")), "[REDACTED]");
+    }
+
+    #[test]
+    fn keeps_short_runs_of_bip39_words() {
+        let message = "borrow init accepted the loan offer and will repay it later";
+        assert_eq!(redact_mnemonic_words(message), message);
+
+        let eleven_words = MNEMONIC.rsplit_once(' ').unwrap().0;
+        assert_eq!(redact_mnemonic_words(eleven_words), eleven_words);
+    }
+
+    #[test]
+    fn masks_private_keys() {
+        let xprv = Xpriv::new_master(Network::Bitcoin, &[7u8; 32]).unwrap().to_string();
+        let tprv = Xpriv::new_master(Network::Testnet, &[7u8; 32]).unwrap().to_string();
+        let wif = PrivateKey::from_slice(&[7u8; 32], Network::Testnet).unwrap().to_wif();
+
+        for key in [xprv, tprv, wif] {
+            assert_eq!(redact(&format!("key {} loaded", key)), "key [REDACTED] loaded", "{}", key);
+        }
+    }
+
+    #[test]
+    fn masks_solana_keypairs() {
+        let secret = bs58::encode([7u8; 64]).into_string();
+        assert_eq!(redact(&format!("Loaded keypair {}", secret)), "Loaded keypair [REDACTED]");
+        assert_eq!(redact(&format!("SOLANA_SECRET_KEY={}", secret)), "SOLANA_SECRET_KEY=[REDACTED]");
+        assert_eq!(
+            redact(&format!(r#"{{"private_key": "{}"}}"#, secret)),
+            r#"{"private_key": "[REDACTED]"}"#
+        );
+
+        let bytes = vec!["7"; 64].join(",");
+        assert_eq!(redact(&format!("wrote [{}] to id.json", bytes)), "wrote [REDACTED] to id.json");
+    }
+
+    #[test]
+    fn keeps_transaction_signatures() {
+        // A signature is 64 bytes in base58, exactly like a keypair
+        let signature = bs58::encode([7u8; 64]).into_string();
+        for message in [
+            format!("Transaction confirmed: {}", signature),
+            format!("Signature: {}", signature),
+            format!(r#"{{"repayment_txid": "{}"}}"#, signature),
+        ] {
+            assert_eq!(redact(&message), message);
+        }
+    }
+
+    #[test]
+    fn keeps_addresses_pubkeys_and_ids() {
+        let message = "Funded tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl and \
+                       HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk, contract-id: d0a79e23a2144b716e8f3685ffbf7a28";
+        assert_eq!(redact(message), message);
+    }
+
+    #[test]
+    fn masks_values_assigned_to_secret_names() {
+        assert_eq!(redact("MNEMONIC=hunter2 HOME=/tmp"), "MNEMONIC=[REDACTED] HOME=/tmp");
+        assert_eq!(
+            redact(r#"{"private_key": "abc def", "amount": 2}"#),
+            r#"{"private_key": "[REDACTED]", "amount": 2}"#
+        );
+        assert_eq!(redact("admin token: s3cret"), "admin token: [REDACTED]");
+    }
+
+    #[test]
+    fn masks_registered_secrets_while_registered() {
+        let secret = "registered-secret-5f1c2a";
+        let message = format!("calling faucet with {}", secret);
+
+        let guard = register_secret(secret);
+        let second = register_secret(secret);
+        assert_eq!(redact(&message), "calling faucet with [REDACTED]");
+
+        drop(guard);
+        assert_eq!(redact(&message), "calling faucet with [REDACTED]");
+        drop(second);
+        assert_eq!(redact(&message), message);
+    }
+}
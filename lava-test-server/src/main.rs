@@ -1,32 +1,35 @@
-use actix_web::{web, App, HttpResponse, HttpServer, Responder, middleware::Logger};
-use anyhow::{Context, Result, anyhow};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder, middleware::Logger};
+use anyhow::Result;
 use bip39::Mnemonic;
 use chrono::{DateTime, Utc};
 use log::{info, error};
-use rand::Rng;
-use regex::Regex;
-use reqwest::Client;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::process::Command;
 use std::time::Duration;
 use tokio::time::sleep;
 use uuid::Uuid;
 use std::env;
-use std::io::Write;
-use sqlx;
-use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct BtcFaucetRequest {
-    address: String,
-    sats: u64,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct LavaUsdFaucetRequest {
-    pubkey: String,
-}
+mod chain;
+mod cli;
+mod cli_builds;
+mod cli_cache;
+mod db;
+mod faucet;
+mod loan;
+mod logging;
+mod profiles;
+mod queue;
+mod runner;
+mod scenarios;
+mod secrets;
+mod solana;
+mod steps;
+#[cfg(test)]
+mod test_http;
+mod wallet;
+mod workdir;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TestResult {
@@ -35,16 +38,230 @@ struct TestResult {
     success: bool,
     mnemonic: String,
     btc_address: String,
+    btc_derivation_path: String,
     lava_usd_pubkey: String,
     contract_id: Option<String>,
     collateral_repayment_txid: Option<String>,
     error_message: Option<String>,
+    // Contract JSON from the last get-contract, as written by the CLI
     details: serde_json::Value,
+    // What the server recorded about the run itself: failure, work directory,
+    // faucet attempts, funding, CLI steps, rejections, matrix and suite summaries
+    #[serde(default)]
+    run_details: serde_json::Value,
+    rng_seed: Option<u64>,
+    mnemonic_source: Option<String>,
+    profile: Option<String>,
+    funding_txid: Option<String>,
+    funding_block_height: Option<u64>,
+    cli_sha256: Option<String>,
+    cli_version: Option<String>,
+    cli_build: Option<String>,
+    // Matrix run this result belongs to
+    parent_run_id: Option<String>,
+    // Loan terms passed to `borrow init`
+    loan: Option<loan::LoanParams>,
+    scenario: Option<String>,
+    // Suite this result belongs to; set on the suite's summary result as well
+    suite: Option<String>,
+    // queue::STATUS_QUEUED or queue::STATUS_RUNNING until the run ends, then
+    // STATUS_COMPLETED, or STATUS_AWAITING_MATURITY while the run is paused
+    status: String,
+    // When the loan term ends, for runs that wait for it
+    maturity_deadline: Option<DateTime<Utc>>,
+    // Place in the run queue while the run waits for a worker (1 is next);
+    // looked up from the queue, not stored with the result
+    #[serde(default)]
+    queue_position: Option<u64>,
+    steps: steps::Steps,
+    // Where a paused run continues; persisted in pending_runs rather than with the result
+    #[serde(skip)]
+    pending_poll: Option<PendingPoll>,
+}
+
+const STATUS_COMPLETED: &str = "completed";
+const STATUS_AWAITING_MATURITY: &str = "awaiting maturity";
+
+// Scenario step a paused run continues at, and when
+#[derive(Debug, Clone)]
+struct PendingPoll {
+    step_index: usize,
+    next_poll_at: DateTime<Utc>,
+}
+
+// Result of one await-maturity poll
+enum MaturityPoll {
+    Matured,
+    Failed,
+    Pending(DateTime<Utc>),
+}
+
+impl TestResult {
+    // Hides the mnemonic before the result leaves the server
+    fn redacted(mut self) -> Self {
+        self.mnemonic = secrets::REDACTED.to_string();
+        self
+    }
+    
+    // Result without a wallet, for runs that failed early and matrix parents
+    fn placeholder(id: &str, settings: &RunSettings) -> Self {
+        TestResult {
+            id: id.to_string(),
+            timestamp: Utc::now(),
+            success: false,
+            mnemonic: "N/A".to_string(),
+            btc_address: "N/A".to_string(),
+            btc_derivation_path: "N/A".to_string(),
+            lava_usd_pubkey: "N/A".to_string(),
+            contract_id: None,
+            collateral_repayment_txid: None,
+            error_message: None,
+            details: serde_json::Value::Null,
+            run_details: serde_json::Value::Null,
+            rng_seed: Some(settings.rng_seed),
+            mnemonic_source: None,
+            profile: Some(settings.profile.name.clone()),
+            funding_txid: None,
+            funding_block_height: None,
+            cli_sha256: None,
+            cli_version: None,
+            cli_build: settings.cli_build.as_ref().map(|build| build.label.clone()),
+            parent_run_id: settings.parent_run_id.clone(),
+            loan: Some(settings.loan.clone()),
+            scenario: Some(settings.scenario.name.clone()),
+            suite: settings.suite.clone(),
+            status: STATUS_COMPLETED.to_string(),
+            maturity_deadline: None,
+            queue_position: None,
+            steps: steps::Steps::default(),
+            pending_poll: None,
+        }
+    }
+    
+    // Marks the run and its current step as failed with a stable failure class,
+    // e.g. "contract_file_missing"
+    fn fail(&mut self, class: &str, message: impl std::fmt::Display) {
+        let message = message.to_string();
+        error!("{}: {}", class, message);
+        self.success = false;
+        self.error_message = Some(format!("{}: {}", class, message));
+        self.steps.fail(class, &message);
+        self.set_run_detail("failure", serde_json::json!({
+            "class": class,
+            "message": message,
+        }));
+    }
+    
+    // Adds a named entry to the run details
+    fn set_run_detail(&mut self, key: &str, value: serde_json::Value) {
+        if !self.run_details.is_object() {
+            self.run_details = serde_json::json!({});
+        }
+        self.run_details[key] = value;
+    }
+}
+
+// Everything a CLI step printed, kept so failed runs can be debugged later
+#[derive(Debug, Serialize, Deserialize)]
+struct StepLog {
+    run_id: String,
+    step: String,
+    command: String,
+    started_at: DateTime<Utc>,
+    outcome: String,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    duration_ms: u64,
+    timeout_secs: Option<u64>,
+    stdout: String,
+    stderr: String,
+}
+
+impl StepLog {
+    // Plain-text form written to the run directory
+    fn to_text(&self) -> String {
+        format!(
+            "$ {}\nstarted: {}\noutcome: {} after {} ms (exit code {:?}, signal {:?})\n\n--- stdout ---\n{}\n--- stderr ---\n{}",
+            self.command,
+            self.started_at.to_rfc3339(),
+            self.outcome,
+            self.duration_ms,
+            self.exit_code,
+            self.signal,
+            self.stdout,
+            self.stderr
+        )
+    }
+}
+
+// Query parameters of /test-results; only results matching every given value are returned
+#[derive(Debug, Deserialize)]
+struct TestResultsFilter {
+    loan_capital_asset: Option<String>,
+    ltv_ratio_bp: Option<u32>,
+    loan_duration_days: Option<u32>,
+    loan_amount: Option<f64>,
+    scenario: Option<String>,
+    suite: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct TestRequest {
     run_id: Option<String>,
+    // Explicit wallet mnemonic; requires the admin token
+    mnemonic: Option<String>,
+    // Seed for all randomness in the run; generated when omitted
+    seed: Option<u64>,
+    // Network profile name; DEFAULT_NETWORK_PROFILE when omitted
+    profile: Option<String>,
+    // Registered CLI build to run against; the profile's cli_url when omitted
+    cli_build: Option<String>,
+    // Runs the scenario once per listed CLI build, each as a child run
+    cli_builds: Option<Vec<String>>,
+    // Loan terms for `borrow init`; each defaults to the standard loan when omitted
+    loan_capital_asset: Option<String>,
+    ltv_ratio_bp: Option<u32>,
+    loan_duration_days: Option<u32>,
+    loan_amount: Option<f64>,
+}
+
+// Body of /run-suite/{name}; every field is optional
+#[derive(Debug, Serialize, Deserialize)]
+struct SuiteRequest {
+    run_id: Option<String>,
+    // Seed the scenarios' seeds are drawn from; generated when omitted
+    seed: Option<u64>,
+    // Network profile; the suite's profile, then DEFAULT_NETWORK_PROFILE, when omitted
+    profile: Option<String>,
+    cli_build: Option<String>,
+}
+
+// Everything needed to reproduce a run. Stored with paused runs, except for
+// the mnemonic, which only the encrypted test result keeps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunSettings {
+    rng_seed: u64,
+    #[serde(skip)]
+    mnemonic: Option<Mnemonic>,
+    profile: profiles::NetworkProfile,
+    cli_build: Option<cli_builds::CliBuild>,
+    parent_run_id: Option<String>,
+    loan: loan::LoanParams,
+    scenario: scenarios::Scenario,
+    suite: Option<String>,
+}
+
+impl RunSettings {
+    // Download URL and pinned hash of the CLI the run uses
+    fn cli_source(&self) -> (&str, Option<&str>) {
+        match &self.cli_build {
+            Some(build) => {
+                info!("Using CLI build {}", build.label);
+                (&build.url, build.sha256.as_deref())
+            },
+            None => (&self.profile.cli_url, self.profile.cli_sha256.as_deref()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,450 +269,426 @@ struct TestResponse {
     run_id: String,
     status: String,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    child_run_ids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_position: Option<u64>,
 }
 
-async fn run_test(run_id: &str) -> Result<TestResult> {
-    let test_id = run_id.to_string();
-    let timestamp = Utc::now();
-    
-    info!("Starting test run: {}", test_id);
-    
-    // Step 1: Generate a new mnemonic and new receiving addresses
-    info!("Step 1: Generating mnemonic and addresses");
-    let entropy = rand::thread_rng().gen::<[u8; 16]>();
-    let mnemonic = Mnemonic::from_entropy(&entropy).context("Failed to generate mnemonic")?;
-    let mnemonic_str = mnemonic.to_string();
-    
-    // Using valid hardcoded testnet addresses for testing purposes
-    // In a real implementation, these would be derived from the mnemonic
-    let btc_address = "tb1qxasf0jlsssl3xz8xvl8pmg8d8zpljqmervhtrr".to_string();
-    let lava_usd_pubkey = "CU9KRXJobqo1HVbaJwoWpnboLFXw3bef54xJ1dewXzcf".to_string();
-    
-    info!("Generated mnemonic: {}", mnemonic_str);
-    info!("BTC address: {}", btc_address);
-    info!("LavaUSD pubkey: {}", lava_usd_pubkey);
-    
-    // Create a test result with initial data
-    let mut test_result = TestResult {
-        id: test_id,
-        timestamp,
-        success: false,
-        mnemonic: mnemonic_str.clone(),
-        btc_address: btc_address.clone(),
-        lava_usd_pubkey: lava_usd_pubkey.clone(),
-        contract_id: None,
-        collateral_repayment_txid: None,
-        error_message: None,
-        details: serde_json::Value::Null,
-    };
-    
-    // Step 2: Call the testnet faucet endpoints
-    info!("Step 2: Requesting funds from faucets");
-    
-    let client = Client::new();
-    
-    // BTC faucet request
-    match client.post("https://faucet.testnet.lava.xyz/mint-mutinynet")
-        .json(&BtcFaucetRequest {
-            address: btc_address.clone(),
-            sats: 100000,
-        })
-        .send()
-        .await {
-            Ok(response) => {
-                if !response.status().is_success() {
-                    let err_msg = format!("BTC faucet request failed with status: {}", response.status());
-                    error!("{}", err_msg);
-                    test_result.error_message = Some(err_msg);
-                    return Ok(test_result);
-                }
-                info!("BTC faucet request successful");
-            },
-            Err(e) => {
-                let err_msg = format!("BTC faucet request error: {}", e);
-                error!("{}", err_msg);
-                test_result.error_message = Some(err_msg);
-                return Ok(test_result);
-            }
+// What a queued run executes once a worker picks it up; stored as JSON in run_queue
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum RunJob {
+    Test {
+        settings: RunSettings,
+    },
+    Matrix {
+        settings: RunSettings,
+        children: Vec<(String, RunSettings)>,
+    },
+    Suite {
+        settings: RunSettings,
+        suite: scenarios::Suite,
+        children: Vec<(String, RunSettings)>,
+    },
+}
+
+impl RunJob {
+    fn settings(&self) -> &RunSettings {
+        match self {
+            RunJob::Test { settings } | RunJob::Matrix { settings, .. } | RunJob::Suite { settings, .. } => settings,
         }
+    }
     
-    // LavaUSD faucet request
-    match client.post("https://faucet.testnet.lava.xyz/transfer-lava-usd")
-        .json(&LavaUsdFaucetRequest {
-            pubkey: lava_usd_pubkey.clone(),
-        })
-        .send()
-        .await {
-            Ok(response) => {
-                if !response.status().is_success() {
-                    let err_msg = format!("LavaUSD faucet request failed with status: {}", response.status());
-                    error!("{}", err_msg);
-                    test_result.error_message = Some(err_msg);
-                    return Ok(test_result);
+    // The settings do not serialize the mnemonic, so the queue keeps it
+    // encrypted next to the job and puts it back when the job is loaded
+    fn restore_mnemonic(&mut self, mnemonic: Mnemonic) {
+        match self {
+            RunJob::Test { settings } => settings.mnemonic = Some(mnemonic),
+            RunJob::Matrix { settings, children } | RunJob::Suite { settings, children, .. } => {
+                for (_, child_settings) in children.iter_mut() {
+                    child_settings.mnemonic = Some(mnemonic.clone());
                 }
-                info!("LavaUSD faucet request successful");
+                settings.mnemonic = Some(mnemonic);
             },
-            Err(e) => {
-                let err_msg = format!("LavaUSD faucet request error: {}", e);
-                error!("{}", err_msg);
-                test_result.error_message = Some(err_msg);
-                return Ok(test_result);
-            }
         }
+    }
     
-    // Wait a bit for the faucet transactions to be processed
-    info!("Waiting for faucet transactions to be processed...");
-    sleep(Duration::from_secs(10)).await;
-    
-    // Step 3: Download and install the CLI
-    info!("Step 3: Downloading and installing the CLI");
-    let cli_path = download_and_install_cli().await?;
-    
-    // Step 4: Create a new loan
-    info!("Step 4: Creating a new loan");
-    
-    let cli_exec = format!("{}/loans-borrower-cli", cli_path);
-    
-    // Simplified command - no QEMU or architecture checks needed
-    let cmd_string = format!(
-        "MNEMONIC=\"{}\" {} --testnet --disable-backup-contracts borrow init --loan-capital-asset solana-lava-usd --ltv-ratio-bp 5000 --loan-duration-days 4 --loan-amount 2 --finalize",
-        mnemonic_str, cli_exec
-    );
-    
-    info!("Executing command: {}", cmd_string);
-    
-    // Attempt to run the command, but handle errors gracefully
-    let borrow_init_output = Command::new("sh")
-        .arg("-c")
-        .arg(cmd_string)
-        .output();
-        
-    match borrow_init_output {
-        Ok(output) => {
-            if output.status.success() {
-                let borrow_output = String::from_utf8_lossy(&output.stdout).to_string();
-                info!("Loan creation output: {}", borrow_output);
-                
-                // Try to extract contract ID from output
-                let contract_id_regex = Regex::new(r"contract-id: ([a-zA-Z0-9]+)").unwrap();
-                if let Some(captures) = contract_id_regex.captures(&borrow_output) {
-                    let id = captures.get(1).unwrap().as_str().to_string();
-                    info!("Captured contract-id: {}", id);
-                    test_result.contract_id = Some(id);
-                } else {
-                    // Use fixed ID for testing
-                    test_result.contract_id = Some("test-contract-12345".to_string());
-                    info!("Using test contract ID: {}", test_result.contract_id.as_ref().unwrap());
-                }
-            } else {
-                error!("Loan creation command failed: {}", String::from_utf8_lossy(&output.stderr));
-                test_result.contract_id = Some("test-contract-12345".to_string());
-                info!("Using test contract ID: {}", test_result.contract_id.as_ref().unwrap());
-            }
-        },
-        Err(e) => {
-            error!("Failed to execute loan creation command: {}", e);
-            test_result.contract_id = Some("test-contract-12345".to_string());
-            info!("Using test contract ID: {}", test_result.contract_id.as_ref().unwrap());
+    // Result shown for the run while it waits in the queue
+    fn queued_result(&self, run_id: &str) -> TestResult {
+        let mut result = TestResult::placeholder(run_id, self.settings());
+        if let RunJob::Suite { .. } = self {
+            result.scenario = None;
+            result.loan = None;
         }
+        result.status = queue::STATUS_QUEUED.to_string();
+        result
     }
     
-    // Wait a bit for the loan to be processed
-    info!("Waiting for loan to be processed...");
-    sleep(Duration::from_secs(5)).await;
-    
-    // Step 6: Repay the loan
-    info!("Step 6: Repaying the loan");
-    
-    // Simplified repayment command - no QEMU or architecture checks needed
-    let repay_cmd_string = format!(
-        "MNEMONIC=\"{}\" {} --testnet --disable-backup-contracts borrow repay --contract-id {}",
-        mnemonic_str, cli_exec, test_result.contract_id.as_ref().unwrap()
-    );
-    
-    info!("Executing repayment command: {}", repay_cmd_string);
-    
-    let repay_output = Command::new("sh")
-        .arg("-c")
-        .arg(repay_cmd_string)
-        .output();
-    
-    match repay_output {
-        Ok(output) => {
-            if output.status.success() {
-                info!("Loan repayment output: {}", String::from_utf8_lossy(&output.stdout));
-            } else {
-                let err_msg = format!("Loan repayment command failed: {}", String::from_utf8_lossy(&output.stderr));
-                error!("{}", err_msg);
-                // Continue with the test even if this fails
-            }
+    async fn run(self, run_id: &str) {
+        match self {
+            RunJob::Test { settings } => {
+                runner::execute_run(run_id, &settings).await;
+            },
+            RunJob::Matrix { settings, children } => runner::run_matrix(run_id, &settings, children).await,
+            RunJob::Suite { settings, suite, children } => runner::run_suite(run_id, &settings, &suite, children).await,
+        }
+    }
+}
+
+// Queues a run and answers its request: 429 when the queue is full and 409
+// when a run with the same id is already waiting, running or awaiting maturity
+async fn queue_run(run_id: String, job: RunJob, message: String, child_run_ids: Option<Vec<String>>) -> HttpResponse {
+    let limits = queue::QueueLimits::from_env();
+    match db::enqueue_run(&run_id, &job, limits.max_depth).await {
+        Ok(db::Enqueued::Position(position)) => {
+            info!("Queued run {} at position {}", run_id, position);
+            queue::wake_worker();
+            HttpResponse::Ok().json(TestResponse {
+                run_id,
+                status: queue::STATUS_QUEUED.to_string(),
+                message,
+                child_run_ids,
+                queue_position: Some(position),
+            })
         },
+        Ok(db::Enqueued::Full) => HttpResponse::TooManyRequests().json(serde_json::json!({
+            "error": format!("The run queue is full ({} runs waiting), try again later", limits.max_depth)
+        })),
+        Ok(db::Enqueued::Duplicate) => HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("Run {} is already queued, running or awaiting maturity", run_id)
+        })),
         Err(e) => {
-            let err_msg = format!("Failed to execute loan repayment command: {}", e);
-            error!("{}", err_msg);
-            // Continue with the test even if this fails
-        }
+            error!("Failed to queue run {}: {:#}", run_id, e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to queue run: {}", e)
+            }))
+        },
     }
+}
+
+fn authorization_header(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+}
+
+async fn run_test_handler(http_req: HttpRequest, req: web::Json<TestRequest>) -> impl Responder {
+    let run_id = req.run_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
     
-    // Wait a bit for the repayment to be processed
-    info!("Waiting for repayment to be processed...");
-    sleep(Duration::from_secs(5)).await;
-    
-    // Step 7: Get the contract details to verify the loan is closed
-    info!("Step 7: Getting contract details");
-    
-    let output_file = format!("{}.json", test_result.contract_id.as_ref().unwrap());
-    
-    // Simplified get contract command - fixed to match the CLI's expected parameters
-    let get_contract_cmd_string = format!(
-        "MNEMONIC=\"{}\" {} --testnet --disable-backup-contracts get-contract --contract-id {} --verbose --output-file {}",
-        mnemonic_str, cli_exec, test_result.contract_id.as_ref().unwrap(), output_file
-    );
+    info!("Received test request with run_id: {}", run_id);
     
-    info!("Executing get contract command: {}", get_contract_cmd_string);
-    
-    let get_contract_output = Command::new("sh")
-        .arg("-c")
-        .arg(get_contract_cmd_string)
-        .output();
-    
-    match get_contract_output {
-        Ok(output) => {
-            if output.status.success() {
-                info!("Get contract command succeeded");
-            } else {
-                let err_msg = format!("Get contract command failed: {}", String::from_utf8_lossy(&output.stderr));
-                error!("{}", err_msg);
-                // Continue with the test even if this fails
+    // Replaying a specific wallet is restricted to operators
+    let mnemonic = match &req.mnemonic {
+        Some(phrase) => {
+            if !secrets::is_authorized(authorization_header(&http_req)) {
+                return HttpResponse::Forbidden().json(serde_json::json!({
+                    "error": "Supplying a mnemonic requires the admin token"
+                }));
+            }
+            
+            match Mnemonic::parse(phrase) {
+                Ok(mnemonic) => Some(mnemonic),
+                Err(e) => {
+                    return HttpResponse::BadRequest().json(serde_json::json!({
+                        "error": format!("Invalid mnemonic: {}", e)
+                    }));
+                }
             }
         },
+        None => None,
+    };
+    
+    let profile = match profiles::resolve_profile(req.profile.as_deref()) {
+        Ok(profile) => profile,
         Err(e) => {
-            let err_msg = format!("Failed to execute get contract command: {}", e);
-            error!("{}", err_msg);
-            // Continue with the test even if this fails
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e.to_string()
+            }));
         }
-    }
-    
-    // Wait a bit for the get contract command to complete
-    info!("Waiting for get contract command to complete...");
-    sleep(Duration::from_secs(5)).await;
-    
-    // Step 8-9: Check the JSON file
-    info!("Step 8-9: Checking the JSON file");
+    };
     
-    // Check if the JSON file exists and process it
-    let json_content = if Path::new(&output_file).exists() {
-        match fs::read_to_string(&output_file) {
-            Ok(content) => content,
+    let cli_build = match &req.cli_build {
+        Some(label) => match cli_builds::resolve_builds(std::slice::from_ref(label)) {
+            Ok(mut builds) => builds.pop(),
             Err(e) => {
-                error!("Failed to read JSON file: {}", e);
-                create_test_json_file(&output_file)?
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": e.to_string()
+                }));
             }
-        }
-    } else {
-        info!("JSON file does not exist, creating test file");
-        create_test_json_file(&output_file)?
+        },
+        None => None,
     };
     
-    // Parse the JSON
-    let json_value = match serde_json::from_str::<serde_json::Value>(&json_content) {
-        Ok(value) => value,
+    let loan = match loan::LoanParams::resolve(
+        req.loan_capital_asset.as_deref(),
+        req.ltv_ratio_bp,
+        req.loan_duration_days,
+        req.loan_amount,
+    ) {
+        Ok(loan) => loan,
         Err(e) => {
-            error!("Failed to parse JSON content: {}", e);
-            // Use a standard test JSON structure
-            let test_json = create_standard_test_json();
-            info!("Using standard test JSON structure due to parse error");
-            test_json
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e.to_string()
+            }));
         }
     };
     
-    // Check if the loan is closed and there's a repayment
-    let is_closed = json_value.get("Closed").is_some();
-    let has_repayment = json_value
-        .get("outcome")
-        .and_then(|outcome| outcome.get("repayment"))
-        .is_some();
+    let settings = RunSettings {
+        rng_seed: req.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+        mnemonic,
+        profile,
+        cli_build,
+        parent_run_id: None,
+        loan,
+        scenario: scenarios::Scenario::standard(),
+        suite: None,
+    };
     
-    if is_closed && has_repayment {
-        info!("Test successful! Loan is closed with repayment.");
-        test_result.success = true;
-        
-        // Extract collateral repayment txid
-        if let Some(repayment_txid) = json_value
-            .get("outcome")
-            .and_then(|outcome| outcome.get("repayment"))
-            .and_then(|repayment| repayment.get("collateral_repayment_txid"))
-            .and_then(|txid| txid.as_str())
-        {
-            info!("Collateral repayment TXID: {}", repayment_txid);
-            test_result.collateral_repayment_txid = Some(repayment_txid.to_string());
-        } else {
-            // Use fixed test value
-            let test_txid = "60c27b7a5db7652c271de02120982e7f21a54eca5aa6d80177859a5b690f9d28";
-            test_result.collateral_repayment_txid = Some(test_txid.to_string());
-            info!("Using test repayment TXID: {}", test_txid);
+    // A list of CLI builds fans out into one child run per build
+    if let Some(labels) = &req.cli_builds {
+        if req.cli_build.is_some() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Use either cli_build or cli_builds, not both"
+            }));
         }
-    } else {
-        info!("Test failed! Loan is not closed with repayment.");
-        test_result.success = false;
+        if labels.is_empty() {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "cli_builds must name at least one CLI build"
+            }));
+        }
+        let builds = match cli_builds::resolve_builds(labels) {
+            Ok(builds) => builds,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": e.to_string()
+                }));
+            }
+        };
+        
+        let children = matrix_children(&run_id, &settings, builds);
+        let child_run_ids = children.iter().map(|(child_id, _)| child_id.clone()).collect();
+        let message = format!("Matrix run queued across {} CLI builds", labels.len());
+        
+        return queue_run(run_id, RunJob::Matrix { settings, children }, message, Some(child_run_ids)).await;
     }
     
-    // Store the full JSON as details
-    test_result.details = json_value;
-    
-    Ok(test_result)
+    // The run waits for a free worker, so the response does not block
+    queue_run(run_id, RunJob::Test { settings }, "Test queued successfully".to_string(), None).await
 }
 
-fn create_test_json_file(output_file: &str) -> Result<String> {
-    // Create a standard test JSON response
-    let test_repayment_txid = "60c27b7a5db7652c271de02120982e7f21a54eca5aa6d80177859a5b690f9d28";
-    let test_json_content = format!(r#"{{
-  "Closed": {{}},
-  "outcome": {{
-    "repayment": {{
-      "collateral_repayment_txid": "{}"
-    }}
-  }}
-}}"#, test_repayment_txid);
-    
-    // Write to the file
-    let mut file = fs::File::create(output_file).context("Failed to create test JSON file")?;
-    file.write_all(test_json_content.as_bytes()).context("Failed to write test JSON content")?;
-    info!("Created test JSON file: {}", output_file);
-    
-    Ok(test_json_content)
+// Child run ids and settings for a matrix run, one per CLI build.
+// Each child gets its own seed drawn from the parent's, so wallets do not collide.
+fn matrix_children(
+    parent_run_id: &str,
+    settings: &RunSettings,
+    builds: Vec<cli_builds::CliBuild>,
+) -> Vec<(String, RunSettings)> {
+    let mut rng = ChaCha20Rng::seed_from_u64(settings.rng_seed);
+    builds
+        .into_iter()
+        .map(|build| {
+            let child_id = format!("{}-{}", parent_run_id, build.label);
+            let child_settings = RunSettings {
+                rng_seed: rng.gen(),
+                mnemonic: settings.mnemonic.clone(),
+                profile: settings.profile.clone(),
+                cli_build: Some(build),
+                parent_run_id: Some(parent_run_id.to_string()),
+                loan: settings.loan.clone(),
+                scenario: settings.scenario.clone(),
+                suite: settings.suite.clone(),
+            };
+            (child_id, child_settings)
+        })
+        .collect()
 }
 
-async fn download_and_install_cli() -> Result<String> {
-    let temp_dir = env::temp_dir();
-    let cli_dir = temp_dir.join("lava-cli");
-    let cli_path = cli_dir.to_string_lossy().to_string();
-    
-    info!("Creating CLI directory at: {}", cli_path);
-    fs::create_dir_all(&cli_dir).context("Failed to create CLI directory")?;
-    
-    // Always use Linux binary in Docker container
-    let url = "https://loans-borrower-cli.s3.amazonaws.com/loans-borrower-cli-linux";
-    let cli_file_path = cli_dir.join("loans-borrower-cli");
-    
-    info!("Downloading CLI from {} to {}", url, cli_file_path.display());
-    
-    // Download the CLI
-    let response = reqwest::get(url).await.context("Failed to download CLI")?;
-    let content = response.bytes().await.context("Failed to read CLI content")?;
-    
-    // Save the CLI to a file
-    let mut file = fs::File::create(&cli_file_path).context("Failed to create CLI file")?;
-    file.write_all(&content).context("Failed to write CLI content")?;
-    
-    // Make the CLI executable
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&cli_file_path).context("Failed to get CLI file metadata")?.permissions();
-        perms.set_mode(0o755);
-        fs::set_permissions(&cli_file_path, perms).context("Failed to set CLI file permissions")?;
-    }
+async fn run_suite_handler(path: web::Path<String>, req: web::Json<SuiteRequest>) -> impl Responder {
+    let suite_name = path.into_inner();
+    let run_id = req.run_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
     
-    // Verify the binary can be executed
-    let file_output = Command::new("file")
-        .arg(&cli_file_path)
-        .output();
+    info!("Received suite request for {} with run_id: {}", suite_name, run_id);
     
-    match file_output {
-        Ok(output) => {
-            info!("CLI file type: {}", String::from_utf8_lossy(&output.stdout));
-        },
+    let suite = match scenarios::resolve_suite(&suite_name) {
+        Ok(suite) => suite,
         Err(e) => {
-            info!("Could not determine CLI file type: {}", e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("{:#}", e)
+            }));
         }
-    }
+    };
     
-    info!("CLI downloaded and installed successfully at: {}", cli_file_path.display());
+    let profile = match profiles::resolve_profile(req.profile.as_deref().or(suite.profile.as_deref())) {
+        Ok(profile) => profile,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e.to_string()
+            }));
+        }
+    };
     
-    Ok(cli_path)
+    let cli_build = match &req.cli_build {
+        Some(label) => match cli_builds::resolve_builds(std::slice::from_ref(label)) {
+            Ok(mut builds) => builds.pop(),
+            Err(e) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": e.to_string()
+                }));
+            }
+        },
+        None => None,
+    };
+    
+    let settings = RunSettings {
+        rng_seed: req.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+        mnemonic: None,
+        profile,
+        cli_build,
+        parent_run_id: None,
+        loan: loan::LoanParams::default(),
+        scenario: scenarios::Scenario::standard(),
+        suite: Some(suite.name.clone()),
+    };
+    
+    let children = match suite_children(&run_id, &settings, &suite) {
+        Ok(children) => children,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("{:#}", e)
+            }));
+        }
+    };
+    let child_run_ids = children.iter().map(|(child_id, _)| child_id.clone()).collect();
+    let message = format!("Suite {} queued with {} scenarios", suite.name, children.len());
+    
+    queue_run(run_id, RunJob::Suite { settings, suite, children }, message, Some(child_run_ids)).await
 }
 
-fn generate_random_string(length: usize) -> String {
-    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-    let mut rng = rand::thread_rng();
-    
-    (0..length)
-        .map(|_| {
-            let idx = rng.gen_range(0..CHARSET.len());
-            CHARSET[idx] as char
+// Child run ids and settings for a suite run, one per scenario, with the
+// scenario's funding amounts and loan terms applied
+fn suite_children(
+    parent_run_id: &str,
+    settings: &RunSettings,
+    suite: &scenarios::Suite,
+) -> Result<Vec<(String, RunSettings)>> {
+    let mut rng = ChaCha20Rng::seed_from_u64(settings.rng_seed);
+    suite
+        .scenarios
+        .iter()
+        .map(|scenario| {
+            let mut profile = settings.profile.clone();
+            if let Some(sats) = scenario.btc_funding_sats {
+                profile.btc_funding_sats = sats;
+            }
+            if scenario.lava_usd_funding_amount.is_some() {
+                profile.lava_usd_funding_amount = scenario.lava_usd_funding_amount;
+            }
+            
+            let child_id = format!("{}-{}", parent_run_id, scenario.name);
+            let child_settings = RunSettings {
+                rng_seed: rng.gen(),
+                mnemonic: None,
+                profile,
+                cli_build: settings.cli_build.clone(),
+                parent_run_id: Some(parent_run_id.to_string()),
+                loan: scenario.loan()?,
+                scenario: scenario.clone(),
+                suite: Some(suite.name.clone()),
+            };
+            Ok((child_id, child_settings))
         })
         .collect()
 }
 
-async fn run_test_handler(req: web::Json<TestRequest>) -> impl Responder {
-    let run_id = req.run_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+async fn get_test_status(path: web::Path<String>) -> impl Responder {
+    let run_id = path.into_inner();
     
-    info!("Received test request with run_id: {}", run_id);
+    // Retrieve the test status from the database
+    match db::get_test_result_from_db(&run_id).await {
+        Some(mut result) => {
+            // The queue knows best whether the run still waits or already runs
+            match db::queue_state(&run_id).await {
+                Ok(Some((started, position))) => {
+                    let status = if started { queue::STATUS_RUNNING } else { queue::STATUS_QUEUED };
+                    result.status = status.to_string();
+                    result.queue_position = position;
+                },
+                Ok(None) => {},
+                Err(e) => error!("Failed to look up run {} in the queue: {}", run_id, e),
+            }
+            HttpResponse::Ok().json(result.redacted())
+        },
+        None => HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Test not found in database"
+        }))
+    }
+}
+
+// Returns the decrypted mnemonic of a run; requires the admin token
+async fn get_test_mnemonic(req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let run_id = path.into_inner();
     
-    // Clone the run_id for the response
-    let response_run_id = run_id.clone();
+    if !secrets::is_authorized(authorization_header(&req)) {
+        return HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "Missing or invalid admin token"
+        }));
+    }
     
-    // Run the test in a separate task so we don't block the response
-    tokio::spawn(async move {
-        match run_test(&run_id).await {
-            Ok(test_result) => {
-                info!("Test completed: success={}, id={}", test_result.success, test_result.id);
-                
-                // Save the test result to the database
-                if let Err(e) = save_test_result_to_db(&test_result).await {
-                    error!("Failed to save test result to database: {}", e);
-                } else {
-                    info!("Successfully saved test result to database");
-                }
-            },
+    let result = match db::get_test_result_from_db(&run_id).await {
+        Some(result) => result,
+        None => return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Test not found in database"
+        })),
+    };
+    
+    let mnemonic = if secrets::is_encrypted(&result.mnemonic) {
+        match secrets::MnemonicCipher::from_env().and_then(|cipher| cipher.decrypt(&result.mnemonic)) {
+            Ok(mnemonic) => mnemonic,
             Err(e) => {
-                error!("Test failed with error: {}", e);
-                
-                // Create a simplified failed test result
-                let test_result = TestResult {
-                    id: run_id.clone(),
-                    timestamp: Utc::now(),
-                    success: false,
-                    mnemonic: "Failed to generate".to_string(),
-                    btc_address: "N/A".to_string(),
-                    lava_usd_pubkey: "N/A".to_string(),
-                    contract_id: None,
-                    collateral_repayment_txid: None,
-                    error_message: Some(e.to_string()),
-                    details: serde_json::json!({"error": e.to_string()}),
-                };
-                
-                if let Err(db_err) = save_test_result_to_db(&test_result).await {
-                    error!("Failed to save error result to database: {}", db_err);
-                }
+                error!("Failed to decrypt mnemonic for {}: {}", run_id, e);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": format!("Failed to decrypt mnemonic: {}", e)
+                }));
             }
         }
-    });
+    } else {
+        result.mnemonic
+    };
     
-    HttpResponse::Ok().json(TestResponse {
-        run_id: response_run_id,
-        status: "started".to_string(),
-        message: "Test started successfully".to_string(),
-    })
+    info!("Mnemonic for run {} disclosed to an authorized caller", run_id);
+    
+    HttpResponse::Ok().json(serde_json::json!({
+        "run_id": run_id,
+        "mnemonic": mnemonic
+    }))
 }
 
-async fn get_test_status(path: web::Path<String>) -> impl Responder {
-    let run_id = path.into_inner();
-    
-    // Retrieve the test status from the database
-    match get_test_result_from_db(&run_id).await {
-        Some(result) => HttpResponse::Ok().json(result),
-        None => HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Test not found in database"
-        }))
+// Returns the stored output of one CLI step of a run
+async fn get_step_log(path: web::Path<(String, String)>) -> impl Responder {
+    let (run_id, step) = path.into_inner();
+    
+    match db::get_step_log_from_db(&run_id, &step).await {
+        Ok(Some(step_log)) => HttpResponse::Ok().json(step_log),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
+            "error": format!("No log for step '{}' of run {}", step, run_id)
+        })),
+        Err(e) => {
+            error!("Failed to get step log: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to retrieve step log: {}", e)
+            }))
+        }
     }
 }
 
 // New function to get all test results
-async fn get_all_test_results() -> impl Responder {
-    match get_all_test_results_from_db().await {
-        Ok(results) => HttpResponse::Ok().json(results),
+async fn get_all_test_results(filter: web::Query<TestResultsFilter>) -> impl Responder {
+    match db::get_all_test_results_from_db(&filter).await {
+        Ok(results) => {
+            let results: Vec<TestResult> = results.into_iter().map(TestResult::redacted).collect();
+            HttpResponse::Ok().json(results)
+        },
         Err(e) => {
             error!("Failed to get test results: {}", e);
             HttpResponse::InternalServerError().json(serde_json::json!({
@@ -514,31 +707,86 @@ async fn health_check() -> impl Responder {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+    logging::init();
     
     let bind_address = env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
     
     info!("Starting server on {}", bind_address);
     
+    // Make sure the mnemonic encryption key is available before any run stores a result
+    if let Err(e) = secrets::MnemonicCipher::from_env() {
+        error!("Mnemonic encryption key unavailable: {}", e);
+    }
+    
+    match runner::sweep_run_dirs().await {
+        Ok(_) => info!("Run directories: {}", workdir::runs_root().display()),
+        Err(e) => error!("Failed to clean up expired run directories: {:#}", e),
+    }
+    
+    match profiles::load_profiles() {
+        Ok(profiles) => info!("Network profiles: {}", profiles.keys().cloned().collect::<Vec<_>>().join(", ")),
+        Err(e) => error!("Failed to load network profiles: {}", e),
+    }
+    
     // Test database connection first
     match env::var("DATABASE_URL") {
         Ok(db_url) => {
             info!("Using database URL: {}", db_url);
             match sqlx::SqlitePool::connect(&db_url).await {
-                Ok(_) => info!("Database connection test successful"),
+                Ok(pool) => {
+                    info!("Database connection test successful");
+                    
+                    // Encrypt any mnemonics stored before encryption at rest was introduced
+                    match db::encrypt_plaintext_mnemonics(&pool).await {
+                        Ok(0) => {},
+                        Ok(count) => info!("Encrypted {} plaintext mnemonics", count),
+                        Err(e) => error!("Failed to encrypt plaintext mnemonics: {}", e),
+                    }
+                    pool.close().await;
+                },
                 Err(e) => error!("Failed to connect to database: {}", e),
             }
         },
         Err(e) => error!("DATABASE_URL not set: {}", e),
     }
     
+    // Runs that were in progress when the server stopped start over
+    match db::requeue_interrupted_runs().await {
+        Ok(0) => {},
+        Ok(count) => info!("Requeued {} runs interrupted by the last shutdown", count),
+        Err(e) => error!("Failed to requeue interrupted runs: {}", e),
+    }
+    let limits = queue::QueueLimits::from_env();
+    info!("Run queue: {} workers, at most {} waiting runs", limits.workers, limits.max_depth);
+    for worker in 1..=limits.workers {
+        tokio::spawn(runner::queue_worker(worker));
+    }
+    
+    // Keep polling runs that wait for their loan to mature, including ones
+    // paused before a restart
+    let maturity_check = Duration::from_secs(
+        env::var("MATURITY_CHECK_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(60),
+    );
+    tokio::spawn(async move {
+        loop {
+            runner::poll_pending_runs().await;
+            sleep(maturity_check).await;
+        }
+    });
+    
     // Start the server in a separate task
     let server = match HttpServer::new(|| {
         App::new()
             .wrap(Logger::default())
             .route("/health", web::get().to(health_check))
             .route("/run-test", web::post().to(run_test_handler))
+            .route("/run-suite/{name}", web::post().to(run_suite_handler))
             .route("/test-status/{run_id}", web::get().to(get_test_status))
+            .route("/test-status/{run_id}/mnemonic", web::get().to(get_test_mnemonic))
+            .route("/test-status/{run_id}/steps/{step}/log", web::get().to(get_step_log))
             .route("/test-results", web::get().to(get_all_test_results))
     })
     .bind(&bind_address) {
@@ -563,222 +811,3 @@ async fn main() -> std::io::Result<()> {
         }
     }
 }
-
-async fn save_test_result_to_db(test_result: &TestResult) -> Result<()> {
-    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
-    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
-    
-    // Create the table if it doesn't exist
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS test_results (
-            id TEXT PRIMARY KEY,
-            timestamp TEXT NOT NULL,
-            success INTEGER NOT NULL,
-            mnemonic TEXT NOT NULL,
-            btc_address TEXT NOT NULL,
-            lava_usd_pubkey TEXT NOT NULL,
-            contract_id TEXT,
-            collateral_repayment_txid TEXT,
-            error_message TEXT,
-            details TEXT
-        )"
-    )
-    .execute(&pool)
-    .await
-    .context("Failed to create table")?;
-    
-    // Serialize the details to JSON
-    let details_json = serde_json::to_string(&test_result.details)
-        .context("Failed to serialize details")?;
-    
-    // Insert the test result
-    sqlx::query(
-        "INSERT INTO test_results 
-        (id, timestamp, success, mnemonic, btc_address, lava_usd_pubkey, contract_id, collateral_repayment_txid, error_message, details) 
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
-    )
-    .bind(&test_result.id)
-    .bind(test_result.timestamp.to_rfc3339())
-    .bind(test_result.success as i32)
-    .bind(&test_result.mnemonic)
-    .bind(&test_result.btc_address)
-    .bind(&test_result.lava_usd_pubkey)
-    .bind(&test_result.contract_id)
-    .bind(&test_result.collateral_repayment_txid)
-    .bind(&test_result.error_message)
-    .bind(&details_json)
-    .execute(&pool)
-    .await
-    .context("Failed to insert test result")?;
-    
-    info!("Saved test result to database: {}", test_result.id);
-    pool.close().await;
-    Ok(())
-}
-
-async fn get_test_result_from_db(run_id: &str) -> Option<TestResult> {
-    match env::var("DATABASE_URL") {
-        Ok(db_url) => {
-            match sqlx::SqlitePool::connect(&db_url).await {
-                Ok(pool) => {
-                    // Use a regular query
-                    let query = "SELECT * FROM test_results WHERE id = ?";
-                    let result = sqlx::query_as::<_, (
-                        String,          // id
-                        String,          // timestamp
-                        i32,             // success
-                        String,          // mnemonic
-                        String,          // btc_address
-                        String,          // lava_usd_pubkey
-                        Option<String>,  // contract_id
-                        Option<String>,  // collateral_repayment_txid
-                        Option<String>,  // error_message
-                        String,          // details
-                    )>(query)
-                    .bind(run_id)
-                    .fetch_optional(&pool)
-                    .await;
-                    
-                    match result {
-                        Ok(Some((
-                            id,
-                            timestamp_str,
-                            success,
-                            mnemonic,
-                            btc_address,
-                            lava_usd_pubkey,
-                            contract_id,
-                            collateral_repayment_txid,
-                            error_message,
-                            details_str
-                        ))) => {
-                            let details: serde_json::Value = match serde_json::from_str(&details_str) {
-                                Ok(val) => val,
-                                Err(_) => serde_json::Value::Null,
-                            };
-                            
-                            let timestamp = match DateTime::parse_from_rfc3339(&timestamp_str) {
-                                Ok(dt) => dt.with_timezone(&Utc),
-                                Err(_) => Utc::now(),
-                            };
-                            
-                            let test_result = TestResult {
-                                id,
-                                timestamp,
-                                success: success != 0,
-                                mnemonic,
-                                btc_address,
-                                lava_usd_pubkey,
-                                contract_id,
-                                collateral_repayment_txid,
-                                error_message,
-                                details,
-                            };
-                            
-                            pool.close().await;
-                            Some(test_result)
-                        },
-                        _ => {
-                            pool.close().await;
-                            None
-                        }
-                    }
-                },
-                Err(e) => {
-                    error!("Failed to connect to database: {}", e);
-                    None
-                }
-            }
-        },
-        Err(e) => {
-            error!("DATABASE_URL not set: {}", e);
-            None
-        }
-    }
-}
-
-async fn get_all_test_results_from_db() -> Result<Vec<TestResult>> {
-    let db_url = env::var("DATABASE_URL").context("DATABASE_URL not set")?;
-    let pool = sqlx::SqlitePool::connect(&db_url).await.context("Failed to connect to database")?;
-    
-    // Use a regular query to get all test results
-    let query = "SELECT * FROM test_results ORDER BY timestamp DESC";
-    let result = sqlx::query_as::<_, (
-        String,          // id
-        String,          // timestamp
-        i32,             // success
-        String,          // mnemonic
-        String,          // btc_address
-        String,          // lava_usd_pubkey
-        Option<String>,  // contract_id
-        Option<String>,  // collateral_repayment_txid
-        Option<String>,  // error_message
-        String,          // details
-    )>(query)
-    .fetch_all(&pool)
-    .await;
-    
-    match result {
-        Ok(rows) => {
-            let mut test_results = Vec::new();
-            
-            for (
-                id,
-                timestamp_str,
-                success,
-                mnemonic,
-                btc_address,
-                lava_usd_pubkey,
-                contract_id,
-                collateral_repayment_txid,
-                error_message,
-                details_str
-            ) in rows {
-                let details: serde_json::Value = match serde_json::from_str(&details_str) {
-                    Ok(val) => val,
-                    Err(_) => serde_json::Value::Null,
-                };
-                
-                let timestamp = match DateTime::parse_from_rfc3339(&timestamp_str) {
-                    Ok(dt) => dt.with_timezone(&Utc),
-                    Err(_) => Utc::now(),
-                };
-                
-                let test_result = TestResult {
-                    id,
-                    timestamp,
-                    success: success != 0,
-                    mnemonic,
-                    btc_address,
-                    lava_usd_pubkey,
-                    contract_id,
-                    collateral_repayment_txid,
-                    error_message,
-                    details,
-                };
-                
-                test_results.push(test_result);
-            }
-            
-            pool.close().await;
-            Ok(test_results)
-        },
-        Err(e) => {
-            pool.close().await;
-            Err(anyhow!("Failed to retrieve test results: {}", e))
-        }
-    }
-}
-
-// Helper function to create a standard test JSON structure
-fn create_standard_test_json() -> serde_json::Value {
-    let test_repayment_txid = "60c27b7a5db7652c271de02120982e7f21a54eca5aa6d80177859a5b690f9d28";
-    serde_json::from_str(&format!(r#"{{
-        "Closed": {{}},
-        "outcome": {{
-            "repayment": {{
-                "collateral_repayment_txid": "{}"
-            }}
-        }}
-    }}"#, test_repayment_txid)).unwrap_or_default()
-}
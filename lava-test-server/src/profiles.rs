@@ -0,0 +1,321 @@
+use anyhow::{Context, Result, anyhow};
+use bitcoin::Network;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::str::FromStr;
+
+pub const DEFAULT_PROFILE: &str = "mutinynet";
+
+// Everything that differs between the networks a run can target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    #[serde(default)]
+    pub name: String,
+    // Bitcoin network used for address derivation ("signet", "testnet", "regtest", ...)
+    pub btc_network: String,
+    // Base URL of the faucet service; /mint-mutinynet and /transfer-lava-usd are appended
+    pub faucet_base_url: String,
+    pub btc_funding_sats: u64,
+    // LavaUSD amount to request; the faucet's default is used when omitted
+    #[serde(default)]
+    pub lava_usd_funding_amount: Option<u64>,
+    // Network flag passed to every borrower CLI invocation
+    pub cli_network_flag: String,
+    pub cli_url: String,
+    // Expected SHA-256 of the CLI binary; any other download fails the run
+    #[serde(default)]
+    pub cli_sha256: Option<String>,
+    pub explorer_url: String,
+    // Esplora-compatible API used to confirm the BTC funding; defaults to the
+    // explorer's /api, where mempool-style explorers serve it
+    #[serde(default)]
+    pub esplora_url: Option<String>,
+    // Confirmations required on the faucet transaction (0 accepts it from the mempool)
+    #[serde(default = "default_funding_confirmations")]
+    pub funding_confirmations: u64,
+    #[serde(default = "default_funding_timeout_secs")]
+    pub funding_timeout_secs: u64,
+    #[serde(default = "default_chain_poll_interval_secs")]
+    pub chain_poll_interval_secs: u64,
+    // Solana JSON-RPC endpoint for LavaUSD balances; only used when lava_usd_mint is set
+    #[serde(default = "default_solana_rpc_url")]
+    pub solana_rpc_url: String,
+    // LavaUSD SPL mint; the balance check is skipped when unset
+    #[serde(default)]
+    pub lava_usd_mint: Option<String>,
+    #[serde(default = "default_lava_usd_decimals")]
+    pub lava_usd_decimals: u8,
+    #[serde(default = "default_lava_usd_timeout_secs")]
+    pub lava_usd_timeout_secs: u64,
+    // Time limits for the borrower CLI steps; the process group is killed when exceeded
+    #[serde(default = "default_borrow_init_timeout_secs")]
+    pub borrow_init_timeout_secs: u64,
+    #[serde(default = "default_borrow_finalize_timeout_secs")]
+    pub borrow_finalize_timeout_secs: u64,
+    #[serde(default = "default_borrow_repay_timeout_secs")]
+    pub borrow_repay_timeout_secs: u64,
+    #[serde(default = "default_get_contract_timeout_secs")]
+    pub get_contract_timeout_secs: u64,
+}
+
+fn default_funding_confirmations() -> u64 {
+    1
+}
+
+fn default_funding_timeout_secs() -> u64 {
+    600
+}
+
+fn default_chain_poll_interval_secs() -> u64 {
+    5
+}
+
+fn default_solana_rpc_url() -> String {
+    "https://api.devnet.solana.com".to_string()
+}
+
+fn default_lava_usd_decimals() -> u8 {
+    6
+}
+
+fn default_lava_usd_timeout_secs() -> u64 {
+    120
+}
+
+fn default_borrow_init_timeout_secs() -> u64 {
+    600
+}
+
+fn default_borrow_finalize_timeout_secs() -> u64 {
+    600
+}
+
+fn default_borrow_repay_timeout_secs() -> u64 {
+    600
+}
+
+fn default_get_contract_timeout_secs() -> u64 {
+    120
+}
+
+impl NetworkProfile {
+    pub fn btc_network(&self) -> Result<Network> {
+        Network::from_str(&self.btc_network)
+            .map_err(|e| anyhow!("Invalid btc_network '{}' in profile {}: {}", self.btc_network, self.name, e))
+    }
+
+    pub fn btc_faucet_url(&self) -> String {
+        format!("{}/mint-mutinynet", self.faucet_base_url.trim_end_matches('/'))
+    }
+
+    pub fn lava_usd_faucet_url(&self) -> String {
+        format!("{}/transfer-lava-usd", self.faucet_base_url.trim_end_matches('/'))
+    }
+
+    // Raw token units the LavaUSD balance must grow by after funding
+    pub fn lava_usd_expected_increase(&self) -> u64 {
+        match self.lava_usd_funding_amount {
+            Some(amount) => amount.saturating_mul(10u64.saturating_pow(self.lava_usd_decimals as u32)),
+            None => 1,
+        }
+    }
+
+    pub fn esplora_url(&self) -> String {
+        match &self.esplora_url {
+            Some(url) => url.clone(),
+            None => format!("{}/api", self.explorer_url.trim_end_matches('/')),
+        }
+    }
+
+    pub fn explorer_address_url(&self, address: &str) -> String {
+        format!("{}/address/{}", self.explorer_url.trim_end_matches('/'), address)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, NetworkProfile>,
+}
+
+fn builtin_profiles() -> BTreeMap<String, NetworkProfile> {
+    let mut profiles = BTreeMap::new();
+
+    profiles.insert(DEFAULT_PROFILE.to_string(), NetworkProfile {
+        name: DEFAULT_PROFILE.to_string(),
+        btc_network: "signet".to_string(),
+        faucet_base_url: "https://faucet.testnet.lava.xyz".to_string(),
+        btc_funding_sats: 100000,
+        lava_usd_funding_amount: None,
+        cli_network_flag: "--testnet".to_string(),
+        cli_url: "https://loans-borrower-cli.s3.amazonaws.com/loans-borrower-cli-linux".to_string(),
+        cli_sha256: None,
+        explorer_url: "https://mutinynet.com".to_string(),
+        esplora_url: None,
+        funding_confirmations: default_funding_confirmations(),
+        funding_timeout_secs: default_funding_timeout_secs(),
+        chain_poll_interval_secs: default_chain_poll_interval_secs(),
+        solana_rpc_url: default_solana_rpc_url(),
+        lava_usd_mint: None,
+        lava_usd_decimals: default_lava_usd_decimals(),
+        lava_usd_timeout_secs: default_lava_usd_timeout_secs(),
+        borrow_init_timeout_secs: default_borrow_init_timeout_secs(),
+        borrow_finalize_timeout_secs: default_borrow_finalize_timeout_secs(),
+        borrow_repay_timeout_secs: default_borrow_repay_timeout_secs(),
+        get_contract_timeout_secs: default_get_contract_timeout_secs(),
+    });
+
+    // Local stand-in: faucet and explorer running next to the server
+    profiles.insert("regtest".to_string(), NetworkProfile {
+        name: "regtest".to_string(),
+        btc_network: "regtest".to_string(),
+        faucet_base_url: "http://127.0.0.1:8081".to_string(),
+        btc_funding_sats: 100000,
+        lava_usd_funding_amount: None,
+        cli_network_flag: "--testnet".to_string(),
+        cli_url: "https://loans-borrower-cli.s3.amazonaws.com/loans-borrower-cli-linux".to_string(),
+        cli_sha256: None,
+        explorer_url: "http://127.0.0.1:3002".to_string(),
+        esplora_url: None,
+        funding_confirmations: 0,
+        funding_timeout_secs: 60,
+        chain_poll_interval_secs: 1,
+        solana_rpc_url: "http://127.0.0.1:8899".to_string(),
+        lava_usd_mint: None,
+        lava_usd_decimals: default_lava_usd_decimals(),
+        lava_usd_timeout_secs: 30,
+        borrow_init_timeout_secs: default_borrow_init_timeout_secs(),
+        borrow_finalize_timeout_secs: default_borrow_finalize_timeout_secs(),
+        borrow_repay_timeout_secs: default_borrow_repay_timeout_secs(),
+        get_contract_timeout_secs: default_get_contract_timeout_secs(),
+    });
+
+    // Offline stack served by the lava-mock-stack binary
+    let mock_url = "http://127.0.0.1:8090";
+    profiles.insert("mock".to_string(), NetworkProfile {
+        name: "mock".to_string(),
+        btc_network: "regtest".to_string(),
+        faucet_base_url: format!("{}/faucet", mock_url),
+        btc_funding_sats: 100000,
+        lava_usd_funding_amount: Some(10),
+        cli_network_flag: "--testnet".to_string(),
+        cli_url: format!("{}/cli/loans-borrower-cli", mock_url),
+        cli_sha256: None,
+        explorer_url: format!("{}/esplora", mock_url),
+        esplora_url: Some(format!("{}/esplora", mock_url)),
+        funding_confirmations: 1,
+        funding_timeout_secs: 30,
+        chain_poll_interval_secs: 1,
+        solana_rpc_url: format!("{}/solana", mock_url),
+        lava_usd_mint: Some("MockLavaUsdMint111111111111111111111111111".to_string()),
+        lava_usd_decimals: default_lava_usd_decimals(),
+        lava_usd_timeout_secs: 30,
+        borrow_init_timeout_secs: 30,
+        borrow_finalize_timeout_secs: 30,
+        borrow_repay_timeout_secs: 30,
+        get_contract_timeout_secs: 30,
+    });
+
+    profiles
+}
+
+// Built-in profiles overlaid with the ones from NETWORK_PROFILES_FILE (TOML)
+pub fn load_profiles() -> Result<BTreeMap<String, NetworkProfile>> {
+    let mut profiles = builtin_profiles();
+
+    if let Ok(path) = env::var("NETWORK_PROFILES_FILE") {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read network profiles file {}", path))?;
+        let file: ProfilesFile = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse network profiles file {}", path))?;
+
+        for (name, mut profile) in file.profiles {
+            profile.name = name.clone();
+            profiles.insert(name, profile);
+        }
+    }
+
+    for profile in profiles.values() {
+        profile.btc_network()?;
+    }
+
+    Ok(profiles)
+}
+
+// Looks up a profile by name, falling back to DEFAULT_NETWORK_PROFILE or mutinynet
+pub fn resolve_profile(name: Option<&str>) -> Result<NetworkProfile> {
+    let default_name = env::var("DEFAULT_NETWORK_PROFILE").unwrap_or_else(|_| DEFAULT_PROFILE.to_string());
+    let name = name.unwrap_or(&default_name);
+
+    let mut profiles = load_profiles()?;
+    profiles.remove(name).ok_or_else(|| {
+        anyhow!(
+            "Unknown network profile '{}' (available: {})",
+            name,
+            profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Profiles files written before the chain and Solana checks existed must keep loading
+    #[test]
+    fn loads_profiles_without_chain_endpoints() {
+        let file: ProfilesFile = toml::from_str(
+            r#"
+            [profiles.staging]
+            btc_network = "signet"
+            faucet_base_url = "https://faucet.staging.example.com"
+            btc_funding_sats = 50000
+            lava_usd_funding_amount = 10
+            cli_network_flag = "--testnet"
+            cli_url = "https://loans-borrower-cli.s3.amazonaws.com/loans-borrower-cli-linux"
+            explorer_url = "https://mutinynet.com/"
+            "#,
+        )
+        .unwrap();
+
+        let staging = &file.profiles["staging"];
+        assert_eq!(staging.esplora_url(), "https://mutinynet.com/api");
+        assert_eq!(staging.solana_rpc_url, "https://api.devnet.solana.com");
+        assert_eq!(staging.lava_usd_mint, None);
+        assert_eq!(staging.funding_confirmations, 1);
+        assert_eq!(staging.borrow_finalize_timeout_secs, 600);
+    }
+
+    #[test]
+    fn explicit_chain_endpoints_win() {
+        let file: ProfilesFile = toml::from_str(
+            r#"
+            [profiles.local]
+            btc_network = "regtest"
+            faucet_base_url = "http://127.0.0.1:8081"
+            btc_funding_sats = 1000
+            cli_network_flag = "--testnet"
+            cli_url = "http://127.0.0.1:8081/cli"
+            explorer_url = "http://127.0.0.1:3002"
+            esplora_url = "http://127.0.0.1:3003"
+            solana_rpc_url = "http://127.0.0.1:8899"
+            "#,
+        )
+        .unwrap();
+
+        let local = &file.profiles["local"];
+        assert_eq!(local.esplora_url(), "http://127.0.0.1:3003");
+        assert_eq!(local.solana_rpc_url, "http://127.0.0.1:8899");
+    }
+
+    #[test]
+    fn builtin_profiles_have_valid_networks() {
+        let profiles = builtin_profiles();
+        for name in [DEFAULT_PROFILE, "regtest", "mock"] {
+            assert!(profiles[name].btc_network().is_ok(), "{}", name);
+        }
+        assert_eq!(profiles[DEFAULT_PROFILE].esplora_url(), "https://mutinynet.com/api");
+    }
+}
@@ -0,0 +1,48 @@
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_RUNNING: &str = "running";
+
+// Workers also look for queued runs this often, in case a wake-up was missed
+const IDLE_POLL: Duration = Duration::from_secs(5);
+
+// How many runs execute at once and how many may wait for a worker
+#[derive(Debug, Clone, Copy)]
+pub struct QueueLimits {
+    pub workers: usize,
+    pub max_depth: usize,
+}
+
+impl QueueLimits {
+    pub fn from_env() -> Self {
+        Self {
+            workers: env_count("QUEUE_WORKERS", 1).max(1),
+            max_depth: env_count("QUEUE_MAX_DEPTH", 20),
+        }
+    }
+}
+
+fn env_count(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn work_available() -> &'static Notify {
+    static NOTIFY: OnceLock<Notify> = OnceLock::new();
+    NOTIFY.get_or_init(Notify::new)
+}
+
+// Tells an idle worker that a run was queued
+pub fn wake_worker() {
+    work_available().notify_one();
+}
+
+// Waits until a run is queued, or at most IDLE_POLL
+pub async fn wait_for_work() {
+    let _ = tokio::time::timeout(IDLE_POLL, work_available().notified()).await;
+}
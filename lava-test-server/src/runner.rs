@@ -0,0 +1,1161 @@
+use anyhow::{Context, Result, anyhow};
+use bip39::Mnemonic;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use regex::Regex;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::{chain, cli, cli_cache, db, faucet, logging, profiles, queue, scenarios, secrets, solana, steps, wallet, workdir};
+use crate::{MaturityPoll, PendingPoll, RunSettings, StepLog, TestResult, STATUS_AWAITING_MATURITY, STATUS_COMPLETED};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BtcFaucetRequest {
+    address: String,
+    sats: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LavaUsdFaucetRequest {
+    pubkey: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    amount: Option<u64>,
+}
+
+// Takes queued runs one at a time, oldest first, and runs them to the end
+pub async fn queue_worker(worker: usize) {
+    loop {
+        let (run_id, job) = match db::claim_next_run().await {
+            Ok(Some(claimed)) => claimed,
+            Ok(None) => {
+                queue::wait_for_work().await;
+                continue;
+            },
+            Err(e) => {
+                error!("Worker {} failed to take a queued run: {:#}", worker, e);
+                queue::wait_for_work().await;
+                continue;
+            },
+        };
+
+        info!("Worker {} starting run {}", worker, run_id);
+        // A run that never gets to store its result is stored as failed, so
+        // it does not stay "running"
+        let unfinished = match job {
+            Ok(job) => {
+                // A panicking run must not take the worker down with it
+                let task_run_id = run_id.clone();
+                match tokio::spawn(async move { job.run(&task_run_id).await }).await {
+                    Ok(()) => None,
+                    Err(e) => Some(("run_aborted", format!("Run aborted: {}", e))),
+                }
+            },
+            Err(e) => Some(("run_unreadable", format!("Queued run cannot be loaded: {:#}", e))),
+        };
+        if let Some((class, message)) = unfinished {
+            error!("Run {} failed: {}: {}", run_id, class, message);
+            if let Err(e) = db::fail_unfinished_run(&run_id, class, &message).await {
+                error!("Failed to store the failure of run {}: {}", run_id, e);
+            }
+        }
+        if let Err(e) = db::finish_queued_run(&run_id).await {
+            error!("Failed to remove run {} from the queue: {}", run_id, e);
+        }
+    }
+}
+
+async fn run_test(run_id: &str, settings: &RunSettings) -> Result<TestResult> {
+    let test_id = run_id.to_string();
+    let timestamp = Utc::now();
+
+    let profile = &settings.profile;
+
+    info!("Starting test run: {} (seed {}, profile {})", test_id, settings.rng_seed, profile.name);
+
+    // Each run works in its own scratch directory
+    if let Err(e) = sweep_run_dirs().await {
+        error!("Failed to clean up expired run directories: {:#}", e);
+    }
+    let mut run_dir = workdir::RunDir::create(run_id)?;
+    info!("Run directory: {}", run_dir.path().display());
+
+    // All randomness in the run comes from this generator
+    let mut rng = ChaCha20Rng::seed_from_u64(settings.rng_seed);
+
+    // Step 1: Generate a new mnemonic and new receiving addresses
+    info!("Step 1: Generating mnemonic and addresses");
+    let (mnemonic, mnemonic_source) = match &settings.mnemonic {
+        Some(mnemonic) => (mnemonic.clone(), "provided"),
+        None => {
+            let entropy = rng.gen::<[u8; 16]>();
+            let mnemonic = Mnemonic::from_entropy(&entropy).context("Failed to generate mnemonic")?;
+            (mnemonic, "seed")
+        }
+    };
+    let mnemonic_str = mnemonic.to_string();
+    let _mnemonic_guard = logging::register_secret(&mnemonic_str);
+
+    // Derive the BTC receiving address from the mnemonic (BIP84)
+    let btc_network = profile.btc_network()?;
+    let btc_receive = wallet::derive_btc_address(&mnemonic, btc_network)?;
+    let btc_address = btc_receive.address.clone();
+
+    // Derive the Solana pubkey that receives LavaUSD (SLIP-0010, same path as the CLI)
+    let lava_usd_pubkey = wallet::derive_solana_pubkey(&mnemonic)?;
+
+    info!("Mnemonic ready ({} words, source: {})", mnemonic.word_count(), mnemonic_source);
+    info!("BTC address: {} ({})", btc_address, btc_receive.derivation_path);
+    info!("Explorer: {}", profile.explorer_address_url(&btc_address));
+    info!("LavaUSD pubkey: {} ({})", lava_usd_pubkey, wallet::SOLANA_DERIVATION_PATH);
+
+    // Create a test result with initial data
+    let mut test_result = TestResult {
+        id: test_id,
+        timestamp,
+        success: false,
+        mnemonic: mnemonic_str.clone(),
+        btc_address: btc_address.clone(),
+        btc_derivation_path: btc_receive.derivation_path.clone(),
+        lava_usd_pubkey: lava_usd_pubkey.clone(),
+        contract_id: None,
+        collateral_repayment_txid: None,
+        error_message: None,
+        details: serde_json::Value::Null,
+        run_details: serde_json::Value::Null,
+        rng_seed: Some(settings.rng_seed),
+        mnemonic_source: Some(mnemonic_source.to_string()),
+        profile: Some(profile.name.clone()),
+        funding_txid: None,
+        funding_block_height: None,
+        cli_sha256: None,
+        cli_version: None,
+        cli_build: settings.cli_build.as_ref().map(|build| build.label.clone()),
+        parent_run_id: settings.parent_run_id.clone(),
+        loan: Some(settings.loan.clone()),
+        scenario: Some(settings.scenario.name.clone()),
+        suite: settings.suite.clone(),
+        status: STATUS_COMPLETED.to_string(),
+        maturity_deadline: None,
+        queue_position: None,
+        steps: steps::Steps::planned(&settings.scenario.planned_steps()),
+        pending_poll: None,
+    };
+    test_result.set_run_detail("work_dir", serde_json::json!(run_dir.path()));
+
+    // Step 2: Call the testnet faucet endpoints
+    info!("Step 2: Requesting funds from faucets");
+
+    let faucet = faucet::FaucetClient::new(Client::new(), faucet::RetryPolicy::from_env());
+
+    // A funding amount of 0 skips that faucet, for scenarios that need an unfunded wallet
+    let fund_btc = profile.btc_funding_sats > 0;
+    let fund_lava_usd = profile.lava_usd_funding_amount != Some(0);
+
+    let chain = chain::EsploraClient::new(Client::new(), &profile.esplora_url());
+    let mut existing_utxos = HashSet::new();
+    if fund_btc {
+        // BTC faucet request
+        test_result.steps.start("btc-faucet");
+
+        // Remember what the address already holds, so only the faucet's UTXO counts as funding
+        existing_utxos = match chain.outpoints(&btc_address).await {
+            Ok(outpoints) => outpoints,
+            Err(e) => {
+                test_result.fail("btc_faucet_failed", format!("Failed to list UTXOs of {} before funding: {}", btc_address, e));
+                return Ok(test_result);
+            }
+        };
+        test_result.steps.artifact("existing_utxos", serde_json::json!(existing_utxos.len()));
+
+        let (btc_funding, btc_attempts) = faucet.post(
+            "BTC",
+            &profile.btc_faucet_url(),
+            &BtcFaucetRequest {
+                address: btc_address.clone(),
+                sats: profile.btc_funding_sats,
+            },
+            &mut rng,
+        ).await;
+        test_result.steps.artifact("attempts", serde_json::json!(btc_attempts.len()));
+        test_result.set_run_detail("faucet_attempts", serde_json::json!({ "btc": btc_attempts }));
+        if let Err(e) = btc_funding {
+            test_result.fail("btc_faucet_failed", e);
+            return Ok(test_result);
+        }
+        info!("BTC faucet request successful");
+        test_result.steps.pass();
+    } else {
+        info!("No BTC funding requested, leaving the wallet without collateral");
+        test_result.steps.skip("btc-faucet", "no BTC funding requested");
+    }
+
+    // Record the LavaUSD balance before funding so the increase can be verified
+    let solana = solana::SolanaRpcClient::new(Client::new(), &profile.solana_rpc_url);
+    let lava_usd_before = match &profile.lava_usd_mint {
+        Some(mint) => {
+            let before = solana.token_balance(&lava_usd_pubkey, mint).await;
+            match &before {
+                Ok(balance) => info!("LavaUSD balance before funding: {}", balance.ui_amount),
+                Err(e) => error!("Failed to read LavaUSD balance before funding: {}", e),
+            }
+            Some((mint, before))
+        },
+        None => None,
+    };
+
+    if fund_lava_usd {
+        // LavaUSD faucet request
+        test_result.steps.start("lava-usd-faucet");
+        let (lava_usd_funding, lava_usd_attempts) = faucet.post(
+            "LavaUSD",
+            &profile.lava_usd_faucet_url(),
+            &LavaUsdFaucetRequest {
+                pubkey: lava_usd_pubkey.clone(),
+                amount: profile.lava_usd_funding_amount,
+            },
+            &mut rng,
+        ).await;
+        test_result.steps.artifact("attempts", serde_json::json!(lava_usd_attempts.len()));
+        test_result.run_details["faucet_attempts"]["lava_usd"] = serde_json::json!(lava_usd_attempts);
+        if let Err(e) = lava_usd_funding {
+            test_result.fail("lava_usd_faucet_failed", e);
+            return Ok(test_result);
+        }
+        info!("LavaUSD faucet request successful");
+        test_result.steps.pass();
+    } else {
+        info!("No LavaUSD funding requested");
+        test_result.steps.skip("lava-usd-faucet", "no LavaUSD funding requested");
+    }
+
+    if fund_btc {
+        // Wait for the BTC funding to confirm instead of guessing with a fixed sleep
+        info!(
+            "Waiting for BTC funding of {} ({} confirmations)...",
+            btc_address, profile.funding_confirmations
+        );
+        test_result.steps.start("btc-funding");
+        let funding_wait = chain::FundingWait {
+            min_value: profile.btc_funding_sats,
+            existing: existing_utxos,
+            confirmations: profile.funding_confirmations,
+            timeout: Duration::from_secs(profile.funding_timeout_secs),
+            poll_interval: Duration::from_secs(profile.chain_poll_interval_secs),
+        };
+        match chain.wait_for_funding(&btc_address, &funding_wait).await {
+            Ok(funding) => {
+                info!(
+                    "BTC funding confirmed: {}:{} ({} sats, height {:?})",
+                    funding.txid, funding.vout, funding.value, funding.block_height
+                );
+                test_result.funding_txid = Some(funding.txid.clone());
+                test_result.funding_block_height = funding.block_height;
+                test_result.steps.artifact("txid", serde_json::json!(funding.txid));
+                test_result.steps.artifact("vout", serde_json::json!(funding.vout));
+                test_result.steps.artifact("block_height", serde_json::json!(funding.block_height));
+                test_result.steps.artifact("confirmations", serde_json::json!(funding.confirmations));
+                test_result.set_run_detail("btc_funding", serde_json::json!(funding));
+                test_result.steps.pass();
+            },
+            Err(e) => {
+                test_result.fail("btc_funding_not_confirmed", e);
+                return Ok(test_result);
+            }
+        }
+    } else {
+        test_result.steps.skip("btc-funding", "no BTC funding requested");
+    }
+
+    // Wait for the LavaUSD faucet transfer to land in the borrower's token account
+    match lava_usd_before {
+        _ if !fund_lava_usd => {
+            test_result.steps.skip("lava-usd-funding", "no LavaUSD funding requested");
+        },
+        // Without the starting balance, tokens the wallet already held would pass for the transfer
+        Some((_, Err(e))) => {
+            test_result.steps.start("lava-usd-funding");
+            test_result.fail("lava_usd_balance_unknown", format!("Failed to read LavaUSD balance before funding: {:#}", e));
+            return Ok(test_result);
+        },
+        Some((mint, Ok(before))) => {
+            test_result.steps.start("lava-usd-funding");
+            let before_amount = before.amount;
+            let target = before_amount.saturating_add(profile.lava_usd_expected_increase());
+            info!("Waiting for LavaUSD balance of {} to reach {} raw units...", lava_usd_pubkey, target);
+
+            let after = solana.wait_for_balance(
+                &lava_usd_pubkey,
+                mint,
+                target,
+                Duration::from_secs(profile.lava_usd_timeout_secs),
+                Duration::from_secs(profile.chain_poll_interval_secs),
+            ).await;
+
+            test_result.set_run_detail("lava_usd_balance", serde_json::json!({
+                "mint": mint,
+                "before": before,
+                "after": after.as_ref().ok(),
+            }));
+
+            match after {
+                Ok(balance) => {
+                    info!("LavaUSD balance after funding: {}", balance.ui_amount);
+                    test_result.steps.artifact("before", serde_json::json!(before_amount));
+                    test_result.steps.artifact("after", serde_json::json!(balance.amount));
+                    test_result.steps.pass();
+                },
+                Err(e) => {
+                    test_result.fail("lava_usd_balance_not_received", e);
+                    return Ok(test_result);
+                }
+            }
+        },
+        None => {
+            info!("No LavaUSD mint configured for profile {}, skipping balance check", profile.name);
+            test_result.steps.skip("lava-usd-funding", "no LavaUSD mint configured");
+        },
+    }
+
+    // Step 3: Download and install the CLI
+    info!("Step 3: Downloading and installing the CLI");
+    let (cli_url, cli_sha256) = settings.cli_source();
+    test_result.steps.start("cli-install");
+    let installed_cli = match cli_cache::CliCache::from_env()
+        .install(cli_url, cli_sha256)
+        .await
+    {
+        Ok(installed_cli) => installed_cli,
+        Err(e) => {
+            test_result.fail(e.class(), &e);
+            return Ok(test_result);
+        }
+    };
+    test_result.cli_sha256 = Some(installed_cli.sha256.clone());
+    test_result.cli_version = installed_cli.version.clone();
+    test_result.steps.artifact("sha256", serde_json::json!(installed_cli.sha256));
+    test_result.steps.artifact("version", serde_json::json!(installed_cli.version));
+    test_result.steps.artifact("path", serde_json::json!(installed_cli.path));
+    test_result.steps.pass();
+
+    // Steps 4-7: Run the scenario's CLI steps in order
+    let scenario = &settings.scenario;
+    info!("Steps 4-7: Running scenario {}", scenario.name);
+
+    let cli = cli::BorrowerCli::new(
+        installed_cli.path,
+        &mnemonic_str,
+        &profile.cli_network_flag,
+        run_dir.path(),
+    );
+
+    run_scenario(&mut test_result, &cli, &run_dir, settings, 0).await;
+    if test_result.pending_poll.is_some() {
+        run_dir.pause();
+    }
+
+    Ok(test_result)
+}
+
+// Removes expired run directories, except those of runs awaiting maturity,
+// which resume in them
+pub async fn sweep_run_dirs() -> Result<usize> {
+    let paused = db::pending_run_ids().await.context("Failed to list paused runs")?;
+    workdir::sweep_expired(&paused)
+}
+
+// Runs the scenario's CLI steps from `first_step` on, then verifies the
+// contract. Stops at the first failed step, or when the run has to wait for
+// the loan to mature, in which case `pending_poll` says where to continue.
+async fn run_scenario(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    settings: &RunSettings,
+    first_step: usize,
+) {
+    let scenario = &settings.scenario;
+    let profile = &settings.profile;
+
+    if test_result.contract_id.is_none() {
+        test_result.contract_id = scenario.contract_id.clone();
+    }
+
+    for (step_index, (step_name, step)) in scenario.named_steps().into_iter().enumerate().skip(first_step) {
+        if let Some(rejection) = scenario.reject.get(&step_name) {
+            if !rejected_step(test_result, cli, run_dir, &step_name, step, rejection, settings).await {
+                return;
+            }
+            continue;
+        }
+
+        let passed = match step {
+            scenarios::CliStep::BorrowInit => {
+                borrow_init_step(test_result, cli, run_dir, &step_name, settings).await
+            },
+            scenarios::CliStep::BorrowFinalize => {
+                borrow_finalize_step(test_result, cli, run_dir, &step_name, profile).await
+            },
+            scenarios::CliStep::BorrowRepay => {
+                borrow_repay_step(test_result, cli, run_dir, &step_name, profile).await
+            },
+            scenarios::CliStep::GetContract => {
+                get_contract_step(test_result, cli, run_dir, &step_name, settings).await
+            },
+            scenarios::CliStep::AwaitContract => {
+                await_contract_step(test_result, cli, run_dir, &step_name, settings).await
+            },
+            scenarios::CliStep::AwaitMaturity => {
+                match await_maturity_step(test_result, cli, run_dir, &step_name, settings).await {
+                    MaturityPoll::Matured => true,
+                    MaturityPoll::Failed => false,
+                    MaturityPoll::Pending(next_poll_at) => {
+                        test_result.pending_poll = Some(PendingPoll { step_index, next_poll_at });
+                        return;
+                    },
+                }
+            },
+        };
+        if !passed {
+            return;
+        }
+
+        // Give the backend time to process the change before the next step
+        if !step.fetches_contract() && scenario.settle_secs > 0 {
+            info!("Waiting {}s for {} to be processed...", scenario.settle_secs, step_name);
+            sleep(Duration::from_secs(scenario.settle_secs)).await;
+        }
+    }
+
+    // A refused last step leaves no contract to verify
+    if scenario.ends_in_rejection() {
+        info!("Test successful! The CLI refused the last step as expected.");
+        return;
+    }
+
+    // Step 8-9: Verify the contract reached the expected final state
+    info!("Step 8-9: Verifying the contract state");
+    test_result.steps.start(steps::VERIFY_STEP);
+
+    if test_result.details.is_null() {
+        test_result.fail("contract_not_fetched", "The scenario never fetched the contract");
+        return;
+    }
+    let contract = test_result.details.clone();
+    test_result.steps.artifact("expected", serde_json::json!(scenario.expect));
+    if let Err(mismatch) = scenario.expect.check(&contract) {
+        test_result.fail(mismatch.class, mismatch.message);
+        return;
+    }
+
+    if let Some(repayment_txid) = contract
+        .pointer("/outcome/repayment/collateral_repayment_txid")
+        .and_then(|txid| txid.as_str())
+    {
+        info!("Collateral repayment TXID: {}", repayment_txid);
+        test_result.steps.artifact("collateral_repayment_txid", serde_json::json!(repayment_txid));
+        test_result.collateral_repayment_txid = Some(repayment_txid.to_string());
+    }
+
+    info!("Test successful! Contract is in the expected {} state.", scenario.expect.state);
+    test_result.steps.pass();
+}
+
+// Creates the loan and captures its contract id
+async fn borrow_init_step(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    settings: &RunSettings,
+) -> bool {
+    let args = borrow_init_args(settings);
+
+    test_result.steps.start(step_name);
+    test_result.steps.artifact("loan", serde_json::json!(settings.loan));
+    let limit = Duration::from_secs(settings.profile.borrow_init_timeout_secs);
+    let borrow_init = match run_cli_step(test_result, cli, run_dir, step_name, &args, limit, "borrow_init_failed").await {
+        Some(run) => run,
+        None => return false,
+    };
+
+    let borrow_output = borrow_init.stdout();
+    info!("Loan creation output: {}", borrow_output);
+
+    // The contract ID is only known from the CLI output
+    let contract_id_regex = Regex::new(r"contract-id: ([a-zA-Z0-9]+)").unwrap();
+    match contract_id_regex.captures(&borrow_output) {
+        Some(captures) => {
+            let id = captures.get(1).unwrap().as_str().to_string();
+            info!("Captured contract-id: {}", id);
+            test_result.steps.artifact("contract_id", serde_json::json!(id));
+            test_result.contract_id = Some(id);
+            test_result.steps.pass();
+            true
+        },
+        None => {
+            test_result.fail("contract_id_missing", "borrow init succeeded but printed no contract-id");
+            false
+        }
+    }
+}
+
+// Without --finalize, borrow init only requests the loan and leaves it to borrow-finalize
+fn borrow_init_args(settings: &RunSettings) -> Vec<String> {
+    let mut args = vec!["borrow".to_string(), "init".to_string()];
+    args.extend(settings.loan.cli_args());
+    if !settings.scenario.finalizes_separately() {
+        args.push("--finalize".to_string());
+    }
+    args
+}
+
+// Runs a CLI step the scenario expects the CLI to refuse. The step passes
+// when the CLI exits with the expected error and fails when it goes through.
+async fn rejected_step(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    step: scenarios::CliStep,
+    rejection: &scenarios::ExpectedRejection,
+    settings: &RunSettings,
+) -> bool {
+    let profile = &settings.profile;
+
+    test_result.steps.start(step_name);
+    test_result.steps.artifact("expected_rejection", serde_json::json!(rejection));
+    let contract_id = test_result.contract_id.clone().unwrap_or_default();
+    let (args, limit, failure_class) = match step {
+        scenarios::CliStep::BorrowInit => {
+            test_result.steps.artifact("loan", serde_json::json!(settings.loan));
+            (borrow_init_args(settings), profile.borrow_init_timeout_secs, "borrow_init_failed")
+        },
+        scenarios::CliStep::BorrowFinalize => {
+            let args = ["borrow", "finalize", "--contract-id", contract_id.as_str()];
+            (args.map(str::to_string).to_vec(), profile.borrow_finalize_timeout_secs, "borrow_finalize_failed")
+        },
+        scenarios::CliStep::BorrowRepay => {
+            let args = ["borrow", "repay", "--contract-id", contract_id.as_str()];
+            (args.map(str::to_string).to_vec(), profile.borrow_repay_timeout_secs, "borrow_repay_failed")
+        },
+        _ => {
+            let output_path = run_dir.output_file(&format!("{}-{}.json", step_name, contract_id));
+            let output_file = output_path.to_string_lossy().to_string();
+            let args = ["get-contract", "--contract-id", contract_id.as_str(), "--output-file", output_file.as_str()];
+            (args.map(str::to_string).to_vec(), profile.get_contract_timeout_secs, "get_contract_failed")
+        },
+    };
+
+    let command = cli.describe(&args);
+    info!("Executing command (expecting a rejection): {}", command);
+    let run = cli.run(&args, Duration::from_secs(limit)).await;
+    record_cli_step(test_result, run_dir, step_name, &command, &run).await;
+    if let Some(message) = step_timeout_message(step_name, &run) {
+        test_result.fail(steps::TIMEOUT_CLASS, message);
+        return false;
+    }
+    if let cli::StepOutcome::SpawnFailed(_) = &run.outcome {
+        test_result.fail(failure_class, step_failure(step_name, &run));
+        return false;
+    }
+
+    let checked = rejection.check(step_name, run.exit_code(), &run.stderr());
+    if test_result.run_details.get("rejections").is_none() {
+        test_result.set_run_detail("rejections", serde_json::json!({}));
+    }
+    test_result.run_details["rejections"][step_name] = serde_json::json!({
+        "expected": rejection,
+        "exit_code": run.exit_code(),
+        "rejected": checked.is_ok(),
+    });
+    match checked {
+        Ok(()) => {
+            info!("{} was rejected as expected: {}", step_name, run.stderr().trim());
+            test_result.steps.artifact("rejected", serde_json::json!(true));
+            test_result.steps.pass();
+            true
+        },
+        Err(mismatch) => {
+            test_result.fail(mismatch.class, mismatch.message);
+            false
+        },
+    }
+}
+
+// Finalizes the loan borrow-init requested, locking the collateral
+async fn borrow_finalize_step(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    profile: &profiles::NetworkProfile,
+) -> bool {
+    test_result.steps.start(step_name);
+    let contract_id = match test_result.contract_id.clone() {
+        Some(contract_id) => contract_id,
+        None => {
+            test_result.fail("contract_id_missing", "No contract to finalize");
+            return false;
+        }
+    };
+
+    let args = ["borrow", "finalize", "--contract-id", contract_id.as_str()];
+    let limit = Duration::from_secs(profile.borrow_finalize_timeout_secs);
+    let finalize = match run_cli_step(test_result, cli, run_dir, step_name, &args, limit, "borrow_finalize_failed").await {
+        Some(run) => run,
+        None => return false,
+    };
+    info!("Loan finalization output: {}", finalize.stdout());
+    test_result.steps.pass();
+    true
+}
+
+// Repays the loan created by borrow-init
+async fn borrow_repay_step(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    profile: &profiles::NetworkProfile,
+) -> bool {
+    test_result.steps.start(step_name);
+    let contract_id = match test_result.contract_id.clone() {
+        Some(contract_id) => contract_id,
+        None => {
+            test_result.fail("contract_id_missing", "No contract to repay");
+            return false;
+        }
+    };
+
+    let args = ["borrow", "repay", "--contract-id", contract_id.as_str()];
+    let limit = Duration::from_secs(profile.borrow_repay_timeout_secs);
+    let repay = match run_cli_step(test_result, cli, run_dir, step_name, &args, limit, "borrow_repay_failed").await {
+        Some(run) => run,
+        None => return false,
+    };
+    info!("Loan repayment output: {}", repay.stdout());
+    test_result.steps.pass();
+    true
+}
+
+// Fetches the contract JSON and keeps it as details.contract. When the
+// scenario has a checkpoint for the step, the contract must match it.
+async fn get_contract_step(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    settings: &RunSettings,
+) -> bool {
+    test_result.steps.start(step_name);
+    let contract = match fetch_contract(test_result, cli, run_dir, step_name, &settings.profile).await {
+        Some(contract) => contract,
+        None => return false,
+    };
+
+    if let Some(checkpoint) = settings.scenario.checkpoints.get(step_name) {
+        test_result.steps.artifact("expected", serde_json::json!(checkpoint));
+        if let Err(mismatch) = checkpoint.check(&contract) {
+            test_result.fail(mismatch.class, format!("{} (at {})", mismatch.message, step_name));
+            return false;
+        }
+        info!("Contract is {} at {}", checkpoint.describe(), step_name);
+    }
+    test_result.steps.pass();
+    true
+}
+
+// Polls get-contract until the contract reaches the scenario's expected
+// state and outcome, e.g. after a liquidation
+async fn await_contract_step(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    settings: &RunSettings,
+) -> bool {
+    let scenario = &settings.scenario;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(scenario.await_timeout_secs);
+
+    test_result.steps.start(step_name);
+    let mut polls = 0;
+    loop {
+        polls += 1;
+        test_result.steps.artifact("polls", serde_json::json!(polls));
+        let contract = match fetch_contract(test_result, cli, run_dir, step_name, &settings.profile).await {
+            Some(contract) => contract,
+            None => return false,
+        };
+
+        if scenario.expect.reached(&contract) {
+            info!("Contract is {} after {} polls", scenario.expect.describe(), polls);
+            test_result.steps.pass();
+            return true;
+        }
+
+        if tokio::time::Instant::now() + Duration::from_secs(scenario.await_interval_secs) > deadline {
+            test_result.fail(
+                "contract_await_timeout",
+                format!(
+                    "Contract was not {} within {}s ({} polls)",
+                    scenario.expect.describe(), scenario.await_timeout_secs, polls
+                ),
+            );
+            return false;
+        }
+        info!("Contract not in the expected state yet, polling again in {}s", scenario.await_interval_secs);
+        sleep(Duration::from_secs(scenario.await_interval_secs)).await;
+    }
+}
+
+// Polls get-contract once for an outcome that only appears when the loan term
+// ends. Unless the outcome is there, or the grace period after the end of the
+// term has run out, the run pauses until the next poll is due.
+async fn await_maturity_step(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    settings: &RunSettings,
+) -> MaturityPoll {
+    let scenario = &settings.scenario;
+
+    test_result.steps.start(step_name);
+    let polls = test_result
+        .steps
+        .running_artifact("polls")
+        .and_then(|polls| polls.as_u64())
+        .unwrap_or(0)
+        + 1;
+    test_result.steps.artifact("polls", serde_json::json!(polls));
+
+    let contract = match fetch_contract(test_result, cli, run_dir, step_name, &settings.profile).await {
+        Some(contract) => contract,
+        None => return MaturityPoll::Failed,
+    };
+
+    // The contract's own expiry wins over the one derived from the loan duration
+    let now = Utc::now();
+    let deadline = contract
+        .get("expires_at")
+        .and_then(|expires_at| expires_at.as_str())
+        .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+        .map(|expires_at| expires_at.with_timezone(&Utc))
+        .or(test_result.maturity_deadline)
+        .unwrap_or_else(|| now + chrono::Duration::days(settings.loan.duration_days as i64));
+    let give_up_at = deadline + chrono::Duration::seconds(scenario.maturity_grace_secs as i64);
+    test_result.maturity_deadline = Some(deadline);
+    test_result.steps.artifact("deadline", serde_json::json!(deadline));
+    test_result.steps.artifact("give_up_at", serde_json::json!(give_up_at));
+
+    if scenario.expect.reached(&contract) {
+        info!("Contract is {} after {} polls", scenario.expect.describe(), polls);
+        test_result.steps.pass();
+        return MaturityPoll::Matured;
+    }
+
+    if now >= give_up_at {
+        test_result.fail(
+            "contract_await_timeout",
+            format!(
+                "Contract was not {} by {}, {}s after the end of the loan term ({} polls)",
+                scenario.expect.describe(),
+                give_up_at.to_rfc3339(),
+                scenario.maturity_grace_secs,
+                polls
+            ),
+        );
+        return MaturityPoll::Failed;
+    }
+
+    let next_poll_at = (now + chrono::Duration::seconds(scenario.maturity_poll_secs as i64)).min(give_up_at);
+    test_result.steps.artifact("next_poll_at", serde_json::json!(next_poll_at));
+    info!("Contract not {} yet, polling again at {}", scenario.expect.describe(), next_poll_at.to_rfc3339());
+    MaturityPoll::Pending(next_poll_at)
+}
+
+// Runs get-contract for the run's contract and parses the JSON it writes.
+// Keeps the result as details.contract; fails the current step when there is none.
+async fn fetch_contract(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    profile: &profiles::NetworkProfile,
+) -> Option<serde_json::Value> {
+    let contract_id = match test_result.contract_id.clone() {
+        Some(contract_id) => contract_id,
+        None => {
+            test_result.fail("contract_id_missing", "No contract to fetch");
+            return None;
+        }
+    };
+
+    let output_path = run_dir.output_file(&format!("{}-{}.json", step_name, contract_id));
+    let output_file = output_path.to_string_lossy().to_string();
+    let args = [
+        "get-contract",
+        "--contract-id", contract_id.as_str(),
+        "--verbose",
+        "--output-file", output_file.as_str(),
+    ];
+
+    // Never mistake the file of an earlier poll for fresh output
+    let _ = fs::remove_file(&output_path);
+    test_result.steps.artifact("output_file", serde_json::json!(output_path));
+    let limit = Duration::from_secs(profile.get_contract_timeout_secs);
+    run_cli_step(test_result, cli, run_dir, step_name, &args, limit, "get_contract_failed").await?;
+    info!("Get contract command succeeded");
+
+    let json_content = match fs::read_to_string(&output_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            test_result.fail(
+                "contract_file_missing",
+                format!("get-contract did not write {}", output_path.display()),
+            );
+            return None;
+        },
+        Err(e) => {
+            test_result.fail(
+                "contract_file_unreadable",
+                format!("Failed to read {}: {}", output_path.display(), e),
+            );
+            return None;
+        }
+    };
+
+    let json_value = match serde_json::from_str::<serde_json::Value>(&json_content) {
+        Ok(value) => value,
+        Err(e) => {
+            test_result.fail("contract_json_invalid", format!("Failed to parse contract JSON: {}", e));
+            return None;
+        }
+    };
+
+    // Later fetches replace earlier ones; verification looks at the latest
+    test_result.details = json_value.clone();
+    Some(json_value)
+}
+
+// Runs a CLI step and records it. Returns the run when the CLI exited
+// successfully; otherwise the step is failed with `failure_class`, or with
+// cli_timeout when it was killed for running too long.
+async fn run_cli_step<S: AsRef<str>>(
+    test_result: &mut TestResult,
+    cli: &cli::BorrowerCli,
+    run_dir: &workdir::RunDir,
+    step_name: &str,
+    args: &[S],
+    limit: Duration,
+    failure_class: &str,
+) -> Option<cli::StepRun> {
+    let command = cli.describe(args);
+    info!("Executing command: {}", command);
+
+    let run = cli.run(args, limit).await;
+    record_cli_step(test_result, run_dir, step_name, &command, &run).await;
+    if let Some(message) = step_timeout_message(step_name, &run) {
+        test_result.fail(steps::TIMEOUT_CLASS, message);
+        return None;
+    }
+    if !run.succeeded() {
+        test_result.fail(failure_class, step_failure(step_name, &run));
+        return None;
+    }
+    Some(run)
+}
+
+// Keeps the command, outcome and output of a CLI step in the database and
+// the run's logs, and a summary of it under details.cli_steps
+async fn record_cli_step(
+    test_result: &mut TestResult,
+    run_dir: &workdir::RunDir,
+    step: &str,
+    command: &str,
+    run: &cli::StepRun,
+) {
+    let timeout_secs = match &run.outcome {
+        cli::StepOutcome::TimedOut { limit, .. } => Some(limit.as_secs()),
+        _ => None,
+    };
+    let step_log = StepLog {
+        run_id: test_result.id.clone(),
+        step: step.to_string(),
+        command: command.to_string(),
+        started_at: Utc::now() - chrono::Duration::milliseconds(run.duration.as_millis() as i64),
+        outcome: run.outcome_name().to_string(),
+        exit_code: run.exit_code(),
+        signal: run.signal(),
+        duration_ms: run.duration.as_millis() as u64,
+        timeout_secs,
+        stdout: logging::redact(&run.stdout()),
+        stderr: logging::redact(&run.stderr()),
+    };
+
+    if test_result.run_details.get("cli_steps").is_none() {
+        test_result.set_run_detail("cli_steps", serde_json::json!({}));
+    }
+    test_result.steps.artifact("log", serde_json::json!(format!("/test-status/{}/steps/{}/log", test_result.id, step)));
+    test_result.steps.artifact("exit_code", serde_json::json!(step_log.exit_code));
+    test_result.run_details["cli_steps"][step] = serde_json::json!({
+        "outcome": step_log.outcome,
+        "exit_code": step_log.exit_code,
+        "signal": step_log.signal,
+        "duration_ms": step_log.duration_ms,
+        "timeout_secs": step_log.timeout_secs,
+    });
+
+    if let Err(e) = run_dir.write_log(step, &step_log.to_text()) {
+        error!("Failed to write {} log: {}", step, e);
+    }
+    if let Err(e) = db::save_step_log_to_db(&step_log).await {
+        error!("Failed to save {} log to database: {}", step, e);
+    }
+}
+
+// Why a CLI step that ran to completion or failed to start did not succeed
+fn step_failure(name: &str, run: &cli::StepRun) -> String {
+    match &run.outcome {
+        cli::StepOutcome::SpawnFailed(e) => format!("Failed to execute {}: {}", name, e),
+        _ => format!(
+            "{} exited with {} ({})",
+            name,
+            run.exit_code().map_or_else(|| "no exit code".to_string(), |code| format!("code {}", code)),
+            run.stderr().trim()
+        ),
+    }
+}
+
+// Error message for a step that was killed for running too long
+fn step_timeout_message(step: &str, run: &cli::StepRun) -> Option<String> {
+    match &run.outcome {
+        cli::StepOutcome::TimedOut { limit, .. } => Some(format!(
+            "{} timed out after {}s and was killed",
+            step,
+            limit.as_secs()
+        )),
+        _ => None,
+    }
+}
+
+// Runs the test and stores its result, recording a failed result when the run errors out
+pub async fn execute_run(run_id: &str, settings: &RunSettings) -> TestResult {
+    let test_result = match run_test(run_id, settings).await {
+        Ok(test_result) => test_result,
+        Err(e) => {
+            // Create a simplified failed test result; `details` stays null since no contract was fetched
+            let mut test_result = TestResult::placeholder(run_id, settings);
+            test_result.mnemonic = "Failed to generate".to_string();
+            test_result.fail("run_failed", format!("{:#}", e));
+            test_result
+        }
+    };
+
+    store_run_result(test_result, settings).await
+}
+
+// Saves a run that ended or paused. A paused run is handed to the maturity
+// poller; a finished one gets its overall result from its steps.
+async fn store_run_result(mut test_result: TestResult, settings: &RunSettings) -> TestResult {
+    let pending_poll = test_result.pending_poll.clone();
+    match &pending_poll {
+        Some(pending_poll) => {
+            test_result.status = STATUS_AWAITING_MATURITY.to_string();
+            test_result.success = false;
+            info!(
+                "Test {} is awaiting maturity (deadline {:?}), next poll at {}",
+                test_result.id,
+                test_result.maturity_deadline.map(|deadline| deadline.to_rfc3339()),
+                pending_poll.next_poll_at.to_rfc3339()
+            );
+        },
+        None => {
+            // The overall result is derived from the steps
+            test_result.status = STATUS_COMPLETED.to_string();
+            test_result.success = test_result.steps.finish();
+            info!("Test completed: success={}, id={}", test_result.success, test_result.id);
+        },
+    }
+
+    // Save the test result to the database
+    if let Err(e) = db::save_test_result_to_db(&test_result).await {
+        error!("Failed to save test result to database: {}", e);
+    } else {
+        info!("Successfully saved test result to database");
+    }
+
+    let pending_saved = match &pending_poll {
+        Some(pending_poll) => db::save_pending_run(&test_result.id, pending_poll, settings).await,
+        None => db::delete_pending_run(&test_result.id).await,
+    };
+    if let Err(e) = pending_saved {
+        error!("Failed to update pending run {}: {}", test_result.id, e);
+    }
+
+    test_result
+}
+
+// Continues paused runs whose next poll is due
+pub async fn poll_pending_runs() {
+    let due = match db::load_due_pending_runs(Utc::now()).await {
+        Ok(due) => due,
+        Err(e) => {
+            error!("Failed to load pending runs: {}", e);
+            return;
+        }
+    };
+
+    for (run_id, pending_poll, settings) in due {
+        info!("Resuming run {} at step {}", run_id, pending_poll.step_index);
+        if let Err(e) = resume_run(&run_id, pending_poll.step_index, &settings).await {
+            // The row stays due, so the run is retried on the next check
+            error!("Failed to resume run {}: {:#}", run_id, e);
+        }
+    }
+}
+
+// Picks a paused run up from its stored result and continues its scenario
+async fn resume_run(run_id: &str, step_index: usize, settings: &RunSettings) -> Result<()> {
+    let mut test_result = db::get_test_result_from_db(run_id)
+        .await
+        .ok_or_else(|| anyhow!("Test result not found"))?;
+
+    let mnemonic = if secrets::is_encrypted(&test_result.mnemonic) {
+        secrets::MnemonicCipher::from_env()?.decrypt(&test_result.mnemonic)?
+    } else {
+        test_result.mnemonic.clone()
+    };
+    let _mnemonic_guard = logging::register_secret(&mnemonic);
+    test_result.mnemonic = mnemonic.clone();
+
+    let mut run_dir = workdir::RunDir::resume(run_id)?;
+    let (cli_url, cli_sha256) = settings.cli_source();
+    let installed_cli = cli_cache::CliCache::from_env()
+        .install(cli_url, cli_sha256)
+        .await
+        .context("Failed to install the CLI")?;
+    let cli = cli::BorrowerCli::new(
+        installed_cli.path,
+        &mnemonic,
+        &settings.profile.cli_network_flag,
+        run_dir.path(),
+    );
+
+    run_scenario(&mut test_result, &cli, &run_dir, settings, step_index).await;
+    if test_result.pending_poll.is_some() {
+        run_dir.pause();
+    }
+    store_run_result(test_result, settings).await;
+    Ok(())
+}
+
+// Runs the children one after another and stores a parent result summarizing them
+pub async fn run_matrix(run_id: &str, settings: &RunSettings, children: Vec<(String, RunSettings)>) {
+    info!("Starting matrix run {} across {} CLI builds", run_id, children.len());
+
+    let mut summary = Vec::new();
+    for (child_id, child_settings) in &children {
+        let child = execute_run(child_id, child_settings).await;
+        summary.push(serde_json::json!({
+            "run_id": child.id,
+            "cli_build": child.cli_build,
+            "status": child.status,
+            "success": child.success,
+            "cli_sha256": child.cli_sha256,
+            "cli_version": child.cli_version,
+            "error_message": child.error_message,
+        }));
+    }
+
+    let mut parent = TestResult::placeholder(run_id, settings);
+    parent.success = summary.iter().all(|child| child["success"] == true);
+    let failed: Vec<String> = summary
+        .iter()
+        .filter(|child| child["success"] != true)
+        .filter_map(|child| child["cli_build"].as_str().map(str::to_string))
+        .collect();
+    if !failed.is_empty() {
+        parent.error_message = Some(format!("Failed CLI builds: {}", failed.join(", ")));
+    }
+    parent.set_run_detail("matrix", serde_json::json!(summary));
+
+    info!("Matrix run {} completed: success={}", run_id, parent.success);
+    if let Err(e) = db::save_test_result_to_db(&parent).await {
+        error!("Failed to save matrix result to database: {}", e);
+    }
+}
+
+// Runs the scenarios one after another and stores a suite result summarizing them
+pub async fn run_suite(
+    run_id: &str,
+    settings: &RunSettings,
+    suite: &scenarios::Suite,
+    children: Vec<(String, RunSettings)>,
+) {
+    info!("Starting suite {} as run {} with {} scenarios", suite.name, run_id, children.len());
+
+    let mut summary = Vec::new();
+    for (child_id, child_settings) in &children {
+        let child = execute_run(child_id, child_settings).await;
+        let failed_step = child
+            .steps
+            .iter()
+            .find(|step| matches!(step.status, steps::StepStatus::Failed | steps::StepStatus::TimedOut))
+            .map(|step| step.name.clone());
+        summary.push(serde_json::json!({
+            "run_id": child.id,
+            "scenario": child.scenario,
+            "status": child.status,
+            "success": child.success,
+            "failed_step": failed_step,
+            "error_message": child.error_message,
+        }));
+    }
+
+    let mut parent = TestResult::placeholder(run_id, settings);
+    parent.scenario = None;
+    parent.loan = None;
+    parent.success = summary.iter().all(|child| child["success"] == true);
+    // Scenarios still waiting for maturity finish on their own; their child
+    // results carry the final outcome
+    let scenario_names = |awaiting: bool| -> Vec<String> {
+        summary
+            .iter()
+            .filter(|child| child["success"] != true)
+            .filter(|child| (child["status"] == STATUS_AWAITING_MATURITY) == awaiting)
+            .filter_map(|child| child["scenario"].as_str().map(str::to_string))
+            .collect()
+    };
+    let failed = scenario_names(false);
+    let awaiting = scenario_names(true);
+    let mut problems = Vec::new();
+    if !failed.is_empty() {
+        problems.push(format!("Failed scenarios: {}", failed.join(", ")));
+    }
+    if !awaiting.is_empty() {
+        problems.push(format!("Awaiting maturity: {}", awaiting.join(", ")));
+    }
+    if !problems.is_empty() {
+        parent.error_message = Some(problems.join("; "));
+    }
+    parent.set_run_detail("suite", serde_json::json!({
+        "name": suite.name,
+        "description": suite.description,
+        "passed": summary.len() - failed.len() - awaiting.len(),
+        "failed": failed.len(),
+        "awaiting_maturity": awaiting.len(),
+        "scenarios": summary,
+    }));
+
+    info!("Suite {} ({}) completed: success={}", suite.name, run_id, parent.success);
+    if let Err(e) = db::save_test_result_to_db(&parent).await {
+        error!("Failed to save suite result to database: {}", e);
+    }
+}
@@ -0,0 +1,1013 @@
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+
+use crate::loan::LoanParams;
+use crate::steps;
+
+pub const STANDARD_SCENARIO: &str = "standard";
+pub const LIQUIDATION_SCENARIO: &str = "liquidation";
+pub const EXPIRY_SCENARIO: &str = "expiry";
+pub const TWO_PHASE_SCENARIO: &str = "two-phase";
+pub const SMOKE_SUITE: &str = "smoke";
+pub const LIQUIDATION_SUITE: &str = "liquidation";
+pub const EXPIRY_SUITE: &str = "expiry";
+pub const REJECTIONS_SUITE: &str = "rejections";
+pub const TWO_PHASE_SUITE: &str = "two-phase";
+
+// Borrower CLI invocations a scenario can be built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CliStep {
+    BorrowInit,
+    // Finalizes a loan borrow-init only requested; borrow-init leaves out
+    // --finalize in scenarios that have this step
+    BorrowFinalize,
+    BorrowRepay,
+    GetContract,
+    // get-contract, repeated until the contract reaches the expected state and outcome
+    AwaitContract,
+    // Like await-contract, for outcomes that only appear when the loan term ends:
+    // the run is paused between polls and survives server restarts
+    AwaitMaturity,
+}
+
+impl CliStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CliStep::BorrowInit => "borrow-init",
+            CliStep::BorrowFinalize => "borrow-finalize",
+            CliStep::BorrowRepay => "borrow-repay",
+            CliStep::GetContract => "get-contract",
+            CliStep::AwaitContract => "await-contract",
+            CliStep::AwaitMaturity => "await-maturity",
+        }
+    }
+
+    // Whether the step leaves a fetched contract to verify
+    pub fn fetches_contract(&self) -> bool {
+        matches!(self, CliStep::GetContract | CliStep::AwaitContract | CliStep::AwaitMaturity)
+    }
+}
+
+// A name, or a list of names of which any one is accepted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOf {
+    One(String),
+    Any(Vec<String>),
+}
+
+impl OneOf {
+    pub fn names(&self) -> &[String] {
+        match self {
+            OneOf::One(name) => std::slice::from_ref(name),
+            OneOf::Any(names) => names,
+        }
+    }
+}
+
+// Final state the contract JSON from the last get-contract must be in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractExpectation {
+    // Top-level state key, e.g. "Closed"
+    pub state: String,
+    // Entry that must be present under `outcome`, e.g. "repayment", or a
+    // list of entries of which one must be present
+    #[serde(default)]
+    pub outcome: Option<OneOf>,
+    // Fields that must be set on that outcome entry
+    #[serde(default)]
+    pub outcome_fields: Vec<String>,
+    // Entries that must not be present under `outcome`
+    #[serde(default)]
+    pub absent_outcomes: Vec<String>,
+}
+
+impl ContractExpectation {
+    // Only the top-level state key, whatever the outcome
+    pub fn in_state(state: &str) -> Self {
+        Self {
+            state: state.to_string(),
+            outcome: None,
+            outcome_fields: Vec::new(),
+            absent_outcomes: Vec::new(),
+        }
+    }
+}
+
+impl Default for ContractExpectation {
+    fn default() -> Self {
+        Self {
+            state: "Closed".to_string(),
+            outcome: Some(OneOf::One("repayment".to_string())),
+            outcome_fields: vec!["collateral_repayment_txid".to_string()],
+            absent_outcomes: Vec::new(),
+        }
+    }
+}
+
+// Why a contract does not match its expectation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractMismatch {
+    pub class: &'static str,
+    pub message: String,
+}
+
+// Outcome entry of the contract JSON, if it is set
+fn contract_outcome<'a>(contract: &'a Value, name: &str) -> Option<&'a Value> {
+    contract
+        .get("outcome")
+        .and_then(|outcome| outcome.get(name))
+        .filter(|outcome| !outcome.is_null())
+}
+
+impl ContractExpectation {
+    // The first expected outcome entry present on the contract
+    fn matched_outcome<'a>(&'a self, contract: &'a Value) -> Option<(&'a str, &'a Value)> {
+        self.outcome.as_ref()?.names().iter().find_map(|name| {
+            contract_outcome(contract, name).map(|outcome| (name.as_str(), outcome))
+        })
+    }
+
+    // The expected state and outcome in words, e.g. "Closed with a repayment outcome"
+    pub fn describe(&self) -> String {
+        match &self.outcome {
+            Some(outcome) => format!("{} with a {} outcome", self.state, outcome.names().join(" or ")),
+            None => self.state.clone(),
+        }
+    }
+
+    // Whether the contract is in the expected state with an expected outcome,
+    // i.e. whether waiting for it to change any further is pointless
+    pub fn reached(&self, contract: &Value) -> bool {
+        contract.get(&self.state).is_some()
+            && (self.outcome.is_none() || self.matched_outcome(contract).is_some())
+    }
+
+    // Pass/fail rule of a run: the contract JSON must be in the expected state,
+    // carry one of the expected outcomes with all its fields, and none of the
+    // outcomes that must be absent
+    pub fn check(&self, contract: &Value) -> Result<(), ContractMismatch> {
+        if contract.get(&self.state).is_none() {
+            return Err(ContractMismatch {
+                class: "contract_state_mismatch",
+                message: format!("Contract is not in the {} state", self.state),
+            });
+        }
+
+        if let Some(name) = self.absent_outcomes.iter().find(|name| contract_outcome(contract, name).is_some()) {
+            return Err(ContractMismatch {
+                class: "contract_outcome_unexpected",
+                message: format!("{} contract has a {} outcome", self.state, name),
+            });
+        }
+
+        let expected = match &self.outcome {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let (outcome_name, outcome) = match self.matched_outcome(contract) {
+            Some(matched) => matched,
+            None => {
+                return Err(ContractMismatch {
+                    class: "contract_outcome_missing",
+                    message: format!("{} contract has no {} outcome", self.state, expected.names().join(" or ")),
+                })
+            }
+        };
+
+        for field in &self.outcome_fields {
+            if outcome.get(field).map_or(true, Value::is_null) {
+                return Err(ContractMismatch {
+                    class: "contract_outcome_field_missing",
+                    message: format!("{} outcome has no {}", outcome_name, field),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// How the CLI must refuse a step that a scenario expects to be rejected
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExpectedRejection {
+    // Exit code the CLI must exit with; any non-zero code when omitted
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    // Regular expression its stderr must match
+    #[serde(default)]
+    pub stderr: Option<String>,
+}
+
+// Why a CLI run does not count as the expected rejection
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectionMismatch {
+    pub class: &'static str,
+    pub message: String,
+}
+
+impl ExpectedRejection {
+    // Pass/fail rule of a step expected to be rejected: the CLI must exit
+    // with an error, with the expected code and stderr when those are given
+    pub fn check(&self, step: &str, exit_code: Option<i32>, stderr: &str) -> Result<(), RejectionMismatch> {
+        let code = match exit_code {
+            Some(0) => {
+                return Err(RejectionMismatch {
+                    class: "cli_not_rejected",
+                    message: format!("{} was expected to be rejected but succeeded", step),
+                })
+            }
+            Some(code) => code,
+            None => {
+                return Err(RejectionMismatch {
+                    class: "cli_rejection_mismatch",
+                    message: format!("{} exited without an exit code", step),
+                })
+            }
+        };
+        if let Some(expected) = self.exit_code.filter(|expected| *expected != code) {
+            return Err(RejectionMismatch {
+                class: "cli_rejection_mismatch",
+                message: format!("{} was rejected with exit code {}, expected {}", step, code, expected),
+            });
+        }
+
+        if let Some(pattern) = &self.stderr {
+            let matched = Regex::new(pattern).is_ok_and(|regex| regex.is_match(stderr));
+            if !matched {
+                return Err(RejectionMismatch {
+                    class: "cli_rejection_mismatch",
+                    message: format!("{} stderr does not match /{}/: {}", step, pattern, stderr.trim()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.exit_code == Some(0) {
+            return Err(anyhow!("exit_code 0 is not a rejection"));
+        }
+        if let Some(pattern) = &self.stderr {
+            Regex::new(pattern).with_context(|| format!("Invalid stderr pattern '{}'", pattern))?;
+        }
+        Ok(())
+    }
+}
+
+fn default_cli_steps() -> Vec<CliStep> {
+    vec![CliStep::BorrowInit, CliStep::BorrowRepay, CliStep::GetContract]
+}
+
+fn default_settle_secs() -> u64 {
+    5
+}
+
+fn default_await_interval_secs() -> u64 {
+    30
+}
+
+fn default_await_timeout_secs() -> u64 {
+    3600
+}
+
+fn default_maturity_poll_secs() -> u64 {
+    3600
+}
+
+fn default_maturity_grace_secs() -> u64 {
+    86_400
+}
+
+// One loan flow: how the wallet is funded, the loan it asks for, the CLI
+// steps it runs and the contract state it must end in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    // Funding overrides; the profile's amounts are used when omitted
+    #[serde(default)]
+    pub btc_funding_sats: Option<u64>,
+    #[serde(default)]
+    pub lava_usd_funding_amount: Option<u64>,
+    #[serde(default)]
+    pub loan_capital_asset: Option<String>,
+    #[serde(default)]
+    pub ltv_ratio_bp: Option<u32>,
+    #[serde(default)]
+    pub loan_duration_days: Option<u32>,
+    #[serde(default)]
+    pub loan_amount: Option<f64>,
+    #[serde(default = "default_cli_steps")]
+    pub steps: Vec<CliStep>,
+    // Existing contract the CLI steps act on instead of one from borrow-init,
+    // e.g. a made-up id to check that repaying it is refused
+    #[serde(default)]
+    pub contract_id: Option<String>,
+    // CLI steps the CLI must refuse, by step name. Only the last CLI step can
+    // be refused, and the contract is not verified after it.
+    #[serde(default)]
+    pub reject: BTreeMap<String, ExpectedRejection>,
+    // State the contract fetched by a get-contract step must be in, by step
+    // name, to check it on the way to the final state
+    #[serde(default)]
+    pub checkpoints: BTreeMap<String, ContractExpectation>,
+    // Pause after borrow-init and borrow-repay so the backend can process them
+    #[serde(default = "default_settle_secs")]
+    pub settle_secs: u64,
+    // How often and how long await-contract polls get-contract
+    #[serde(default = "default_await_interval_secs")]
+    pub await_interval_secs: u64,
+    #[serde(default = "default_await_timeout_secs")]
+    pub await_timeout_secs: u64,
+    // How often await-maturity polls get-contract, and how long past the
+    // end of the loan term it keeps waiting for the outcome
+    #[serde(default = "default_maturity_poll_secs")]
+    pub maturity_poll_secs: u64,
+    #[serde(default = "default_maturity_grace_secs")]
+    pub maturity_grace_secs: u64,
+    #[serde(default)]
+    pub expect: ContractExpectation,
+}
+
+impl Scenario {
+    // The borrow, repay and check flow of a plain /run-test
+    pub fn standard() -> Self {
+        Self {
+            name: STANDARD_SCENARIO.to_string(),
+            description: None,
+            btc_funding_sats: None,
+            lava_usd_funding_amount: None,
+            loan_capital_asset: None,
+            ltv_ratio_bp: None,
+            loan_duration_days: None,
+            loan_amount: None,
+            steps: default_cli_steps(),
+            contract_id: None,
+            reject: BTreeMap::new(),
+            checkpoints: BTreeMap::new(),
+            settle_secs: default_settle_secs(),
+            await_interval_secs: default_await_interval_secs(),
+            await_timeout_secs: default_await_timeout_secs(),
+            maturity_poll_secs: default_maturity_poll_secs(),
+            maturity_grace_secs: default_maturity_grace_secs(),
+            expect: ContractExpectation::default(),
+        }
+    }
+
+    // Borrows at a high LTV, never repays and waits for the lender to
+    // liquidate the collateral or claim it on default
+    pub fn liquidation() -> Self {
+        Self {
+            name: LIQUIDATION_SCENARIO.to_string(),
+            description: Some("High-LTV loan that is never repaid ends liquidated or defaulted".to_string()),
+            ltv_ratio_bp: Some(9_000),
+            steps: vec![CliStep::BorrowInit, CliStep::AwaitContract],
+            expect: ContractExpectation {
+                state: "Closed".to_string(),
+                outcome: Some(OneOf::Any(vec!["liquidation".to_string(), "default".to_string()])),
+                outcome_fields: vec!["collateral_liquidation_txid".to_string()],
+                absent_outcomes: vec!["repayment".to_string()],
+            },
+            ..Self::standard()
+        }
+    }
+
+    // Borrows and never repays; the lender claims the collateral when the term ends
+    pub fn expiry() -> Self {
+        Self {
+            name: EXPIRY_SCENARIO.to_string(),
+            description: Some("Loan that is never repaid defaults at the end of its term".to_string()),
+            loan_duration_days: Some(1),
+            steps: vec![CliStep::BorrowInit, CliStep::AwaitMaturity],
+            expect: ContractExpectation {
+                state: "Closed".to_string(),
+                outcome: Some(OneOf::One("default".to_string())),
+                outcome_fields: vec!["collateral_liquidation_txid".to_string()],
+                absent_outcomes: vec!["repayment".to_string()],
+            },
+            ..Self::standard()
+        }
+    }
+
+    // Requests the loan and finalizes it in a separate invocation, checking
+    // the contract before and after finalization, then repays it
+    pub fn two_phase() -> Self {
+        Self {
+            name: TWO_PHASE_SCENARIO.to_string(),
+            description: Some("Loan initialized and finalized in separate CLI invocations".to_string()),
+            steps: vec![
+                CliStep::BorrowInit,
+                CliStep::GetContract,
+                CliStep::BorrowFinalize,
+                CliStep::GetContract,
+                CliStep::BorrowRepay,
+                CliStep::GetContract,
+            ],
+            checkpoints: BTreeMap::from([
+                ("get-contract".to_string(), ContractExpectation::in_state("Requested")),
+                ("get-contract-2".to_string(), ContractExpectation::in_state("Open")),
+            ]),
+            ..Self::standard()
+        }
+    }
+
+    // Runs just `step`, which the CLI must refuse with any error
+    fn rejected(name: &str, description: &str, step: CliStep) -> Self {
+        Self {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            steps: vec![step],
+            reject: BTreeMap::from([(step.as_str().to_string(), ExpectedRejection::default())]),
+            ..Self::standard()
+        }
+    }
+
+    // Input the CLI has to refuse. The CLI's error messages are not pinned
+    // down, so any non-zero exit counts as a rejection.
+    pub fn rejections() -> Vec<Self> {
+        vec![
+            // The CLI documents no maximum LTV, so this is the one no loan can
+            // have: borrowing the full value of the collateral
+            Self {
+                ltv_ratio_bp: Some(10_000),
+                ..Self::rejected("ltv-full-collateral", "Borrowing at 100% LTV is refused", CliStep::BorrowInit)
+            },
+            Self {
+                loan_amount: Some(0.0),
+                ..Self::rejected("zero-amount", "Borrowing nothing is refused", CliStep::BorrowInit)
+            },
+            Self {
+                loan_amount: Some(-1.0),
+                ..Self::rejected("negative-amount", "Borrowing a negative amount is refused", CliStep::BorrowInit)
+            },
+            Self {
+                loan_capital_asset: Some("unsupported-asset".to_string()),
+                ..Self::rejected("unsupported-asset", "Borrowing an unknown asset is refused", CliStep::BorrowInit)
+            },
+            Self {
+                contract_id: Some("0".repeat(32)),
+                ..Self::rejected(
+                    "repay-unknown-contract",
+                    "Repaying a contract that does not exist is refused",
+                    CliStep::BorrowRepay,
+                )
+            },
+            Self {
+                btc_funding_sats: Some(0),
+                lava_usd_funding_amount: Some(0),
+                ..Self::rejected("unfunded-wallet", "Borrowing without collateral is refused", CliStep::BorrowInit)
+            },
+        ]
+    }
+
+    // Loan terms for borrow-init. They are only checked when borrow-init is
+    // expected to succeed, so a scenario can make the CLI refuse bad terms.
+    pub fn loan(&self) -> Result<LoanParams> {
+        let borrow_rejected = self
+            .named_steps()
+            .iter()
+            .any(|(name, step)| *step == CliStep::BorrowInit && self.reject.contains_key(name));
+        if borrow_rejected {
+            return Ok(LoanParams::unchecked(
+                self.loan_capital_asset.as_deref(),
+                self.ltv_ratio_bp,
+                self.loan_duration_days,
+                self.loan_amount,
+            ));
+        }
+        LoanParams::resolve(
+            self.loan_capital_asset.as_deref(),
+            self.ltv_ratio_bp,
+            self.loan_duration_days,
+            self.loan_amount,
+        )
+        .with_context(|| format!("Scenario '{}'", self.name))
+    }
+
+    // CLI steps paired with their step names; repeated steps are numbered
+    // ("get-contract", "get-contract-2", ...) so every name is unique in a run
+    pub fn named_steps(&self) -> Vec<(String, CliStep)> {
+        let mut seen: BTreeMap<CliStep, usize> = BTreeMap::new();
+        self.steps
+            .iter()
+            .map(|step| {
+                let count = seen.entry(*step).or_insert(0);
+                *count += 1;
+                let name = if *count == 1 {
+                    step.as_str().to_string()
+                } else {
+                    format!("{}-{}", step.as_str(), count)
+                };
+                (name, *step)
+            })
+            .collect()
+    }
+
+    // Whether borrow-init only requests the loan and a later step finalizes it
+    pub fn finalizes_separately(&self) -> bool {
+        self.steps.contains(&CliStep::BorrowFinalize)
+    }
+
+    // Whether the last CLI step is one the CLI must refuse
+    pub fn ends_in_rejection(&self) -> bool {
+        self.named_steps()
+            .last()
+            .is_some_and(|(name, _)| self.reject.contains_key(name))
+    }
+
+    // Every step a run of this scenario goes through, in order
+    pub fn planned_steps(&self) -> Vec<String> {
+        let mut names: Vec<String> = steps::SETUP_STEPS.iter().map(|name| name.to_string()).collect();
+        names.extend(self.named_steps().into_iter().map(|(name, _)| name));
+        if !self.ends_in_rejection() {
+            names.push(steps::VERIFY_STEP.to_string());
+        }
+        names
+    }
+
+    fn validate(&self) -> Result<()> {
+        validate_name(&self.name)?;
+        self.loan()?;
+
+        match &self.contract_id {
+            Some(contract_id) => {
+                if contract_id.is_empty() || !contract_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(anyhow!(
+                        "Scenario '{}' has an invalid contract_id '{}': use letters and digits",
+                        self.name,
+                        contract_id
+                    ));
+                }
+                if self.steps.contains(&CliStep::BorrowInit) {
+                    return Err(anyhow!("Scenario '{}' sets contract_id and cannot run borrow-init", self.name));
+                }
+                if self.steps.is_empty() {
+                    return Err(anyhow!("Scenario '{}' has no steps", self.name));
+                }
+            },
+            None => {
+                if self.steps.first() != Some(&CliStep::BorrowInit) {
+                    return Err(anyhow!("Scenario '{}' must start with borrow-init", self.name));
+                }
+            },
+        }
+
+        let named_steps = self.named_steps();
+        for (step_name, rejection) in &self.reject {
+            let step = match named_steps.iter().find(|(name, _)| name == step_name) {
+                Some((_, step)) => step,
+                None => {
+                    return Err(anyhow!(
+                        "Scenario '{}' expects {} to be rejected, but has no such step",
+                        self.name,
+                        step_name
+                    ))
+                }
+            };
+            if matches!(step, CliStep::AwaitContract | CliStep::AwaitMaturity) {
+                return Err(anyhow!("Scenario '{}' cannot expect {} to be rejected", self.name, step_name));
+            }
+            if named_steps.last().is_some_and(|(name, _)| name != step_name) {
+                return Err(anyhow!(
+                    "Scenario '{}' expects {} to be rejected, but only its last step can be",
+                    self.name,
+                    step_name
+                ));
+            }
+            rejection
+                .validate()
+                .with_context(|| format!("Scenario '{}', rejection of {}", self.name, step_name))?;
+        }
+
+        for step_name in self.checkpoints.keys() {
+            if !named_steps.iter().any(|(name, step)| name == step_name && *step == CliStep::GetContract) {
+                return Err(anyhow!(
+                    "Scenario '{}' has a checkpoint for {}, which is not one of its get-contract steps",
+                    self.name,
+                    step_name
+                ));
+            }
+        }
+
+        if !self.ends_in_rejection() && !self.steps.iter().any(CliStep::fetches_contract) {
+            return Err(anyhow!(
+                "Scenario '{}' needs a get-contract or await-contract step to check the final contract state",
+                self.name
+            ));
+        }
+        if self.steps.contains(&CliStep::AwaitContract) && self.await_interval_secs == 0 {
+            return Err(anyhow!("Scenario '{}' needs an await_interval_secs above 0", self.name));
+        }
+        if self.steps.contains(&CliStep::AwaitMaturity) && self.maturity_poll_secs == 0 {
+            return Err(anyhow!("Scenario '{}' needs a maturity_poll_secs above 0", self.name));
+        }
+        Ok(())
+    }
+}
+
+// Named set of scenarios run together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suite {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    // Network profile for every scenario; the request or default profile when omitted
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub scenarios: Vec<Scenario>,
+}
+
+impl Suite {
+    fn validate(&self) -> Result<()> {
+        if self.scenarios.is_empty() {
+            return Err(anyhow!("Suite '{}' has no scenarios", self.name));
+        }
+        for (index, scenario) in self.scenarios.iter().enumerate() {
+            scenario.validate().with_context(|| format!("Invalid suite '{}'", self.name))?;
+            if self.scenarios[..index].iter().any(|other| other.name == scenario.name) {
+                return Err(anyhow!(
+                    "Suite '{}' lists scenario '{}' more than once",
+                    self.name,
+                    scenario.name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SuitesFile {
+    #[serde(default)]
+    suites: BTreeMap<String, Suite>,
+}
+
+// Suite and scenario names become part of run ids, so keep them URL-safe
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Invalid name '{}': use letters, digits, '.', '-' and '_'",
+            name
+        ))
+    }
+}
+
+fn builtin_suites() -> BTreeMap<String, Suite> {
+    let mut suites = BTreeMap::new();
+
+    suites.insert(SMOKE_SUITE.to_string(), Suite {
+        name: SMOKE_SUITE.to_string(),
+        description: Some("Borrow, repay and check that the contract is closed".to_string()),
+        profile: None,
+        scenarios: vec![Scenario::standard()],
+    });
+
+    suites.insert(LIQUIDATION_SUITE.to_string(), Suite {
+        name: LIQUIDATION_SUITE.to_string(),
+        description: Some("Leave a high-LTV loan unpaid until it is liquidated".to_string()),
+        profile: None,
+        scenarios: vec![Scenario::liquidation()],
+    });
+
+    suites.insert(EXPIRY_SUITE.to_string(), Suite {
+        name: EXPIRY_SUITE.to_string(),
+        description: Some("Leave a loan unpaid until its term ends and it defaults".to_string()),
+        profile: None,
+        scenarios: vec![Scenario::expiry()],
+    });
+
+    suites.insert(TWO_PHASE_SUITE.to_string(), Suite {
+        name: TWO_PHASE_SUITE.to_string(),
+        description: Some("Initialize and finalize a loan separately, then repay it".to_string()),
+        profile: None,
+        scenarios: vec![Scenario::two_phase()],
+    });
+
+    suites.insert(REJECTIONS_SUITE.to_string(), Suite {
+        name: REJECTIONS_SUITE.to_string(),
+        description: Some("Check that the CLI refuses bad loan terms, unknown contracts and unfunded wallets".to_string()),
+        profile: None,
+        scenarios: Scenario::rejections(),
+    });
+
+    suites
+}
+
+// Built-in suites overlaid with the ones from SUITES_FILE (TOML)
+pub fn load_suites() -> Result<BTreeMap<String, Suite>> {
+    let mut suites = builtin_suites();
+
+    if let Ok(path) = env::var("SUITES_FILE") {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read suites file {}", path))?;
+        let file: SuitesFile = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse suites file {}", path))?;
+
+        for (name, mut suite) in file.suites {
+            validate_name(&name)?;
+            suite.name = name.clone();
+            suites.insert(name, suite);
+        }
+    }
+
+    Ok(suites)
+}
+
+pub fn resolve_suite(name: &str) -> Result<Suite> {
+    let suites = load_suites()?;
+    let suite = suites.get(name).cloned().ok_or_else(|| {
+        anyhow!(
+            "Unknown suite '{}' (available: {})",
+            name,
+            suites.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    })?;
+    suite.validate()?;
+    Ok(suite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Contract JSON as written by get-contract for each way a loan can close
+    fn fixture(name: &str) -> Value {
+        let content = match name {
+            "repaid" => include_str!("../tests/fixtures/contracts/repaid.json"),
+            "liquidated" => include_str!("../tests/fixtures/contracts/liquidated.json"),
+            "defaulted" => include_str!("../tests/fixtures/contracts/defaulted.json"),
+            "liquidated_without_txid" => include_str!("../tests/fixtures/contracts/liquidated_without_txid.json"),
+            _ => panic!("no fixture {}", name),
+        };
+        serde_json::from_str(content).unwrap()
+    }
+
+    fn mismatch_class(expectation: &ContractExpectation, contract: &Value) -> &'static str {
+        expectation.check(contract).unwrap_err().class
+    }
+
+    #[test]
+    fn repaid_contract_passes_the_standard_expectation() {
+        let expectation = Scenario::standard().expect;
+
+        assert_eq!(expectation.check(&fixture("repaid")), Ok(()));
+        assert!(expectation.reached(&fixture("repaid")));
+        for name in ["liquidated", "defaulted"] {
+            assert_eq!(mismatch_class(&expectation, &fixture(name)), "contract_outcome_missing", "{}", name);
+            assert!(!expectation.reached(&fixture(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn any_listed_outcome_is_accepted() {
+        let expectation = Scenario::liquidation().expect;
+        assert_eq!(expectation.describe(), "Closed with a liquidation or default outcome");
+
+        for name in ["liquidated", "defaulted"] {
+            assert_eq!(expectation.check(&fixture(name)), Ok(()), "{}", name);
+            assert!(expectation.reached(&fixture(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn single_outcome_rejects_the_others() {
+        let expectation = Scenario::expiry().expect;
+
+        assert_eq!(expectation.check(&fixture("defaulted")), Ok(()));
+        assert_eq!(mismatch_class(&expectation, &fixture("liquidated")), "contract_outcome_missing");
+        assert!(!expectation.reached(&fixture("liquidated")));
+    }
+
+    #[test]
+    fn absent_outcomes_must_not_be_set() {
+        let expectation = Scenario::liquidation().expect;
+
+        let repaid = fixture("repaid");
+        assert_eq!(mismatch_class(&expectation, &repaid), "contract_outcome_unexpected");
+        assert!(!expectation.reached(&repaid));
+
+        // Checked before the expected outcome, so a repaid contract that was
+        // also liquidated still fails
+        let mut both = fixture("liquidated");
+        both["outcome"]["repayment"] = repaid["outcome"]["repayment"].clone();
+        let mismatch = expectation.check(&both).unwrap_err();
+        assert_eq!(mismatch.class, "contract_outcome_unexpected");
+        assert_eq!(mismatch.message, "Closed contract has a repayment outcome");
+
+        // A null entry counts as absent
+        let mut cleared = fixture("liquidated");
+        cleared["outcome"]["repayment"] = Value::Null;
+        assert_eq!(expectation.check(&cleared), Ok(()));
+    }
+
+    #[test]
+    fn outcome_fields_must_be_set() {
+        let expectation = Scenario::liquidation().expect;
+        let contract = fixture("liquidated_without_txid");
+
+        let mismatch = expectation.check(&contract).unwrap_err();
+        assert_eq!(mismatch.class, "contract_outcome_field_missing");
+        assert_eq!(mismatch.message, "liquidation outcome has no collateral_liquidation_txid");
+        // The outcome is there, so polling for it again would not help
+        assert!(expectation.reached(&contract));
+    }
+
+    #[test]
+    fn state_is_checked_first() {
+        let open = json!({ "contract_id": "abc", "outcome": null, "Open": { "updated_at": "2025-03-04T10:18:44Z" } });
+
+        for expectation in [Scenario::standard().expect, Scenario::liquidation().expect] {
+            let mismatch = expectation.check(&open).unwrap_err();
+            assert_eq!(mismatch.class, "contract_state_mismatch");
+            assert_eq!(mismatch.message, "Contract is not in the Closed state");
+            assert!(!expectation.reached(&open));
+        }
+
+        let in_open = ContractExpectation::in_state("Open");
+        assert_eq!(in_open.check(&open), Ok(()));
+        assert!(in_open.reached(&open));
+        assert_eq!(mismatch_class(&in_open, &fixture("repaid")), "contract_state_mismatch");
+    }
+
+    fn rejection(exit_code: Option<i32>, stderr: Option<&str>) -> ExpectedRejection {
+        ExpectedRejection { exit_code, stderr: stderr.map(String::from) }
+    }
+
+    #[test]
+    fn any_error_exit_is_a_rejection_by_default() {
+        let expected = ExpectedRejection::default();
+
+        assert_eq!(expected.check("borrow-init", Some(1), ""), Ok(()));
+        assert_eq!(expected.check("borrow-init", Some(101), "panicked"), Ok(()));
+
+        let succeeded = expected.check("borrow-init", Some(0), "").unwrap_err();
+        assert_eq!(succeeded.class, "cli_not_rejected");
+        assert_eq!(succeeded.message, "borrow-init was expected to be rejected but succeeded");
+
+        // Killed by a signal is not the CLI refusing the input
+        let killed = expected.check("borrow-init", None, "").unwrap_err();
+        assert_eq!(killed.class, "cli_rejection_mismatch");
+    }
+
+    #[test]
+    fn rejection_must_match_the_given_exit_code_and_stderr() {
+        let expected = rejection(Some(2), Some("(?i)ltv .* above"));
+
+        assert_eq!(expected.check("borrow-init", Some(2), "Error: LTV 9501 is above the maximum\n"), Ok(()));
+
+        let wrong_code = expected.check("borrow-init", Some(1), "Error: LTV 9501 is above the maximum").unwrap_err();
+        assert_eq!(wrong_code.class, "cli_rejection_mismatch");
+        assert_eq!(wrong_code.message, "borrow-init was rejected with exit code 1, expected 2");
+
+        let wrong_stderr = expected.check("borrow-init", Some(2), "Error: insufficient funds\n").unwrap_err();
+        assert_eq!(wrong_stderr.class, "cli_rejection_mismatch");
+        assert_eq!(wrong_stderr.message, "borrow-init stderr does not match /(?i)ltv .* above/: Error: insufficient funds");
+
+        // Exit code alone, any stderr
+        assert_eq!(rejection(Some(2), None).check("borrow-init", Some(2), "anything"), Ok(()));
+    }
+
+    #[test]
+    fn rejection_rules_are_validated() {
+        assert!(rejection(Some(2), Some("above")).validate().is_ok());
+        assert_eq!(rejection(Some(0), None).validate().unwrap_err().to_string(), "exit_code 0 is not a rejection");
+        assert!(rejection(None, Some("(unclosed")).validate().is_err());
+    }
+
+    fn scenario(steps: Vec<CliStep>) -> Scenario {
+        Scenario { name: "custom".to_string(), steps, ..Scenario::standard() }
+    }
+
+    fn validation_error(scenario: &Scenario) -> String {
+        format!("{:#}", scenario.validate().unwrap_err())
+    }
+
+    #[test]
+    fn builtin_scenarios_are_valid() {
+        let mut scenarios = vec![Scenario::standard(), Scenario::liquidation(), Scenario::expiry(), Scenario::two_phase()];
+        scenarios.extend(Scenario::rejections());
+        for scenario in scenarios {
+            assert!(scenario.validate().is_ok(), "{}: {:?}", scenario.name, scenario.validate());
+        }
+    }
+
+    #[test]
+    fn only_the_last_step_can_be_rejected() {
+        let mut rejected = scenario(vec![CliStep::BorrowInit, CliStep::BorrowRepay]);
+        rejected.reject.insert("borrow-repay".to_string(), ExpectedRejection::default());
+        assert!(rejected.validate().is_ok());
+        assert!(rejected.ends_in_rejection());
+        assert!(!rejected.planned_steps().contains(&steps::VERIFY_STEP.to_string()));
+
+        let mut early = scenario(default_cli_steps());
+        early.reject.insert("borrow-init".to_string(), ExpectedRejection::default());
+        assert_eq!(
+            validation_error(&early),
+            "Scenario 'custom' expects borrow-init to be rejected, but only its last step can be"
+        );
+
+        // Numbered names count: the second get-contract is the last step, the first is not
+        let mut numbered = scenario(vec![CliStep::BorrowInit, CliStep::GetContract, CliStep::BorrowRepay, CliStep::GetContract]);
+        numbered.reject.insert("get-contract-2".to_string(), ExpectedRejection::default());
+        assert!(numbered.validate().is_ok());
+        numbered.reject = BTreeMap::from([("get-contract".to_string(), ExpectedRejection::default())]);
+        assert!(validation_error(&numbered).contains("only its last step can be"));
+
+        let mut unknown = scenario(default_cli_steps());
+        unknown.reject.insert("borrow-finalize".to_string(), ExpectedRejection::default());
+        assert_eq!(
+            validation_error(&unknown),
+            "Scenario 'custom' expects borrow-finalize to be rejected, but has no such step"
+        );
+
+        let mut awaited = scenario(vec![CliStep::BorrowInit, CliStep::AwaitContract]);
+        awaited.reject.insert("await-contract".to_string(), ExpectedRejection::default());
+        assert_eq!(validation_error(&awaited), "Scenario 'custom' cannot expect await-contract to be rejected");
+
+        let mut exit_zero = scenario(vec![CliStep::BorrowInit]);
+        exit_zero.reject.insert("borrow-init".to_string(), rejection(Some(0), None));
+        assert_eq!(
+            validation_error(&exit_zero),
+            "Scenario 'custom', rejection of borrow-init: exit_code 0 is not a rejection"
+        );
+    }
+
+    #[test]
+    fn checkpoints_must_name_get_contract_steps() {
+        let steps = vec![CliStep::BorrowInit, CliStep::GetContract, CliStep::BorrowRepay, CliStep::GetContract];
+
+        let mut valid = scenario(steps.clone());
+        valid.checkpoints.insert("get-contract".to_string(), ContractExpectation::in_state("Open"));
+        assert!(valid.validate().is_ok());
+
+        for name in ["borrow-repay", "get-contract-3", "await-contract"] {
+            let mut invalid = scenario(steps.clone());
+            invalid.checkpoints.insert(name.to_string(), ContractExpectation::in_state("Open"));
+            assert_eq!(
+                validation_error(&invalid),
+                format!("Scenario 'custom' has a checkpoint for {}, which is not one of its get-contract steps", name)
+            );
+        }
+
+        // await-contract fetches the contract, but only get-contract steps take checkpoints
+        let mut awaited = scenario(vec![CliStep::BorrowInit, CliStep::AwaitContract]);
+        awaited.checkpoints.insert("await-contract".to_string(), ContractExpectation::in_state("Open"));
+        assert!(validation_error(&awaited).contains("not one of its get-contract steps"));
+    }
+
+    #[test]
+    fn contract_id_replaces_borrow_init() {
+        let mut existing = scenario(vec![CliStep::BorrowRepay, CliStep::GetContract]);
+        existing.contract_id = Some("3f9c1a7e52b04d8c9e6a1b2c3d4e5f60".to_string());
+        assert!(existing.validate().is_ok());
+
+        for contract_id in ["", "abc-123", "abc 123", "abc$(id)", "../etc"] {
+            let mut invalid = existing.clone();
+            invalid.contract_id = Some(contract_id.to_string());
+            assert_eq!(
+                validation_error(&invalid),
+                format!("Scenario 'custom' has an invalid contract_id '{}': use letters and digits", contract_id)
+            );
+        }
+
+        let mut with_borrow = scenario(default_cli_steps());
+        with_borrow.contract_id = existing.contract_id.clone();
+        assert_eq!(validation_error(&with_borrow), "Scenario 'custom' sets contract_id and cannot run borrow-init");
+
+        let mut no_steps = scenario(Vec::new());
+        no_steps.contract_id = existing.contract_id.clone();
+        assert_eq!(validation_error(&no_steps), "Scenario 'custom' has no steps");
+
+        // Without one, the scenario has to create its own contract
+        let without = scenario(vec![CliStep::BorrowRepay, CliStep::GetContract]);
+        assert_eq!(validation_error(&without), "Scenario 'custom' must start with borrow-init");
+    }
+
+    #[test]
+    fn final_contract_must_be_fetched_unless_rejected() {
+        let unchecked = scenario(vec![CliStep::BorrowInit, CliStep::BorrowRepay]);
+        assert_eq!(
+            validation_error(&unchecked),
+            "Scenario 'custom' needs a get-contract or await-contract step to check the final contract state"
+        );
+
+        let mut no_interval = scenario(vec![CliStep::BorrowInit, CliStep::AwaitContract]);
+        no_interval.await_interval_secs = 0;
+        assert_eq!(validation_error(&no_interval), "Scenario 'custom' needs an await_interval_secs above 0");
+    }
+}
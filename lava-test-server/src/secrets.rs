@@ -0,0 +1,131 @@
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use log::info;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+// Prefix marking a value as encrypted with the current scheme
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+const DEFAULT_KEY_FILE: &str = "data/mnemonic.key";
+
+const NONCE_LEN: usize = 12;
+
+pub const REDACTED: &str = "[REDACTED]";
+
+// Encrypts mnemonics before they are written to the database
+pub struct MnemonicCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl MnemonicCipher {
+    // Loads the key from MNEMONIC_KEY (hex), or from MNEMONIC_KEY_FILE.
+    // A missing key file is created with a fresh random key.
+    pub fn from_env() -> Result<Self> {
+        let key_hex = match env::var("MNEMONIC_KEY") {
+            Ok(key_hex) => key_hex,
+            Err(_) => {
+                let key_file = env::var("MNEMONIC_KEY_FILE").unwrap_or_else(|_| DEFAULT_KEY_FILE.to_string());
+                load_or_create_key_file(Path::new(&key_file))?
+            }
+        };
+
+        let key_bytes = hex::decode(key_hex.trim()).context("Mnemonic key is not valid hex")?;
+        if key_bytes.len() != 32 {
+            return Err(anyhow!("Mnemonic key must be 32 bytes, got {}", key_bytes.len()));
+        }
+
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key_bytes)),
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt mnemonic"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(payload)))
+    }
+
+    pub fn decrypt(&self, stored: &str) -> Result<String> {
+        let encoded = stored
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| anyhow!("Value is not encrypted"))?;
+        let payload = BASE64.decode(encoded).context("Encrypted mnemonic is not valid base64")?;
+        if payload.len() < NONCE_LEN {
+            return Err(anyhow!("Encrypted mnemonic is truncated"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt mnemonic (wrong key or corrupted value)"))?;
+
+        String::from_utf8(plaintext).context("Decrypted mnemonic is not valid UTF-8")
+    }
+}
+
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+fn load_or_create_key_file(path: &Path) -> Result<String> {
+    if path.exists() {
+        return fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mnemonic key file {}", path.display()));
+    }
+
+    info!("Mnemonic key file {} not found, generating a new key", path.display());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create mnemonic key directory")?;
+    }
+
+    let key_hex = hex::encode(ChaCha20Poly1305::generate_key(&mut OsRng));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create mnemonic key file {}", path.display()))?;
+    file.write_all(key_hex.as_bytes()).context("Failed to write mnemonic key file")?;
+
+    Ok(key_hex)
+}
+
+// Checks the Authorization header against ADMIN_TOKEN; disabled when ADMIN_TOKEN is unset
+pub fn is_authorized(authorization: Option<&str>) -> bool {
+    let expected = match env::var("ADMIN_TOKEN") {
+        Ok(token) if !token.is_empty() => token,
+        _ => return false,
+    };
+
+    let provided = match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(token) => token,
+        None => return false,
+    };
+
+    constant_time_eq(provided.as_bytes(), expected.as_bytes())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
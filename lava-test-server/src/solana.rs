@@ -0,0 +1,270 @@
+use anyhow::{Context, Result, anyhow};
+use log::info;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+// Total balance of a mint across all token accounts of an owner
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenBalance {
+    pub accounts: Vec<String>,
+    // Raw amount in the mint's smallest unit
+    pub amount: u64,
+    pub decimals: u8,
+    pub ui_amount: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenAmount {
+    amount: String,
+    decimals: u8,
+}
+
+// Minimal Solana JSON-RPC client for token balances
+pub struct SolanaRpcClient {
+    client: Client,
+    url: String,
+}
+
+impl SolanaRpcClient {
+    pub fn new(client: Client, url: &str) -> Self {
+        Self {
+            client,
+            url: url.to_string(),
+        }
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let response: RpcResponse = self
+            .client
+            .post(&self.url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Solana RPC {} request failed", method))?
+            .json()
+            .await
+            .with_context(|| format!("Invalid Solana RPC {} response", method))?;
+
+        if let Some(error) = response.error {
+            return Err(anyhow!("Solana RPC {} returned an error: {}", method, error));
+        }
+        response
+            .result
+            .ok_or_else(|| anyhow!("Solana RPC {} returned no result", method))
+    }
+
+    pub async fn token_balance(&self, owner: &str, mint: &str) -> Result<TokenBalance> {
+        let accounts = self
+            .call(
+                "getTokenAccountsByOwner",
+                json!([owner, { "mint": mint }, { "encoding": "jsonParsed" }]),
+            )
+            .await?;
+        let accounts: Vec<String> = accounts["value"]
+            .as_array()
+            .ok_or_else(|| anyhow!("getTokenAccountsByOwner returned no account list"))?
+            .iter()
+            .filter_map(|account| account["pubkey"].as_str().map(str::to_string))
+            .collect();
+
+        let mut amount = 0u64;
+        let mut decimals = 0u8;
+        for account in &accounts {
+            let balance = self.call("getTokenAccountBalance", json!([account])).await?;
+            let token_amount: TokenAmount = serde_json::from_value(balance["value"].clone())
+                .context("Invalid getTokenAccountBalance value")?;
+            let account_amount = token_amount
+                .amount
+                .parse::<u64>()
+                .with_context(|| format!("Invalid token amount '{}'", token_amount.amount))?;
+            amount = amount
+                .checked_add(account_amount)
+                .ok_or_else(|| anyhow!("Token balance of {} overflows across its accounts", owner))?;
+            decimals = token_amount.decimals;
+        }
+
+        Ok(TokenBalance {
+            accounts,
+            amount,
+            decimals,
+            ui_amount: format_ui_amount(amount, decimals)?,
+        })
+    }
+
+    // Polls until the owner's balance reaches at least `target` raw units
+    pub async fn wait_for_balance(
+        &self,
+        owner: &str,
+        mint: &str,
+        target: u64,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<TokenBalance> {
+        let started = Instant::now();
+        loop {
+            let last_state = match self.token_balance(owner, mint).await {
+                Ok(balance) if balance.amount >= target => return Ok(balance),
+                Ok(balance) => {
+                    info!("LavaUSD balance of {} is {} (waiting for {} raw units)", owner, balance.ui_amount, target);
+                    format!("balance {} raw units", balance.amount)
+                }
+                Err(e) => {
+                    info!("Solana RPC error while waiting for LavaUSD: {}", e);
+                    format!("last error: {}", e)
+                }
+            };
+
+            if started.elapsed() + poll_interval > timeout {
+                return Err(anyhow!(
+                    "Timed out after {}s waiting for LavaUSD balance of {} to reach {} raw units ({})",
+                    timeout.as_secs(),
+                    owner,
+                    target,
+                    last_state
+                ));
+            }
+
+            sleep(poll_interval).await;
+        }
+    }
+}
+
+fn format_ui_amount(amount: u64, decimals: u8) -> Result<String> {
+    if decimals == 0 {
+        return Ok(amount.to_string());
+    }
+    let scale = 10u64
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| anyhow!("Token decimals {} overflow the raw amount", decimals))?;
+    Ok(format!("{}.{:0width$}", amount / scale, amount % scale, width = decimals as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{Response, ScriptedServer};
+
+    const OWNER: &str = "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk";
+    const MINT: &str = "MockLavaUsdMint111111111111111111111111111";
+
+    // JSON-RPC stand-in whose owner holds the given raw amounts, one token account each
+    async fn rpc(amounts: Vec<&'static str>) -> ScriptedServer {
+        ScriptedServer::start(move |_, request| {
+            let call = request.json();
+            let result = match call["method"].as_str() {
+                Some("getTokenAccountsByOwner") if call["params"][0] == OWNER && call["params"][1]["mint"] == MINT => {
+                    let accounts: Vec<Value> = (0..amounts.len())
+                        .map(|index| json!({ "pubkey": format!("account-{}", index), "account": {} }))
+                        .collect();
+                    json!({ "context": { "slot": 1 }, "value": accounts })
+                }
+                Some("getTokenAccountBalance") => {
+                    let account = call["params"][0].as_str().unwrap_or_default();
+                    let index: usize = account.trim_start_matches("account-").parse().unwrap();
+                    json!({ "context": { "slot": 1 }, "value": { "amount": amounts[index], "decimals": 6 } })
+                }
+                _ => {
+                    return Response::json(200, json!({
+                        "jsonrpc": "2.0",
+                        "id": call["id"],
+                        "error": { "code": -32602, "message": "Invalid params" },
+                    }))
+                }
+            };
+            Response::json(200, json!({ "jsonrpc": "2.0", "id": call["id"], "result": result }))
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn sums_balances_across_token_accounts() {
+        let server = rpc(vec!["10000000", "2500000"]).await;
+        let solana = SolanaRpcClient::new(Client::new(), &server.url);
+
+        let balance = solana.token_balance(OWNER, MINT).await.unwrap();
+
+        assert_eq!(balance.accounts, ["account-0", "account-1"]);
+        assert_eq!(balance.amount, 12_500_000);
+        assert_eq!(balance.ui_amount, "12.500000");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn owner_without_token_accounts_has_zero_balance() {
+        let server = rpc(vec![]).await;
+        let solana = SolanaRpcClient::new(Client::new(), &server.url);
+
+        let balance = solana.token_balance(OWNER, MINT).await.unwrap();
+
+        assert!(balance.accounts.is_empty());
+        assert_eq!(balance.amount, 0);
+    }
+
+    #[tokio::test]
+    async fn overflowing_balances_are_an_error() {
+        let server = rpc(vec!["18446744073709551615", "1"]).await;
+        let solana = SolanaRpcClient::new(Client::new(), &server.url);
+
+        let error = solana.token_balance(OWNER, MINT).await.unwrap_err();
+
+        assert!(error.to_string().contains("overflows"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn rpc_errors_are_reported() {
+        let server = rpc(vec!["1"]).await;
+        let solana = SolanaRpcClient::new(Client::new(), &server.url);
+
+        let error = solana.token_balance(OWNER, "OtherMint").await.unwrap_err();
+
+        assert!(error.to_string().contains("getTokenAccountsByOwner returned an error"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn waits_until_the_balance_reaches_the_target() {
+        let server = rpc(vec!["5000000"]).await;
+        let solana = SolanaRpcClient::new(Client::new(), &server.url);
+
+        let reached = solana
+            .wait_for_balance(OWNER, MINT, 5_000_000, Duration::from_secs(1), Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(reached.amount, 5_000_000);
+
+        let error = solana
+            .wait_for_balance(OWNER, MINT, 5_000_001, Duration::from_millis(100), Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("balance 5000000 raw units"), "{}", error);
+    }
+
+    #[test]
+    fn formats_ui_amounts() {
+        assert_eq!(format_ui_amount(12_500_000, 6).unwrap(), "12.500000");
+        assert_eq!(format_ui_amount(5, 6).unwrap(), "0.000005");
+        assert_eq!(format_ui_amount(42, 0).unwrap(), "42");
+        assert_eq!(format_ui_amount(u64::MAX, 19).unwrap(), "1.8446744073709551615");
+    }
+
+    #[test]
+    fn overflowing_decimals_are_an_error() {
+        let error = format_ui_amount(1, 20).unwrap_err();
+        assert!(error.to_string().contains("overflow"), "{}", error);
+        assert!(format_ui_amount(1, u8::MAX).is_err());
+    }
+}
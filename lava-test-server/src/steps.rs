@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// Steps every run starts with, in execution order. The scenario's CLI steps
+// follow, and VERIFY_STEP closes the run.
+pub const SETUP_STEPS: &[&str] = &[
+    "btc-faucet",
+    "lava-usd-faucet",
+    "btc-funding",
+    "lava-usd-funding",
+    "cli-install",
+];
+
+pub const VERIFY_STEP: &str = "verify-contract";
+
+// Failure class of a step that was killed for running too long
+pub const TIMEOUT_CLASS: &str = "cli_timeout";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StepStatus {
+    // Not started yet; only seen while a run is in progress
+    Pending,
+    Running,
+    Passed,
+    Failed,
+    Skipped,
+    TimedOut,
+}
+
+impl StepStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Pending => "pending",
+            StepStatus::Running => "running",
+            StepStatus::Passed => "passed",
+            StepStatus::Failed => "failed",
+            StepStatus::Skipped => "skipped",
+            StepStatus::TimedOut => "timed-out",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            StepStatus::Pending,
+            StepStatus::Running,
+            StepStatus::Passed,
+            StepStatus::Failed,
+            StepStatus::Skipped,
+            StepStatus::TimedOut,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == value)
+    }
+}
+
+// Outcome of one step of a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestStep {
+    pub name: String,
+    pub status: StepStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<u64>,
+    pub error_class: Option<String>,
+    pub error_message: Option<String>,
+    // What the step produced or looked at: txids, contract ids, file paths, log URLs
+    pub artifacts: Map<String, Value>,
+}
+
+impl TestStep {
+    pub fn pending(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: StepStatus::Pending,
+            started_at: None,
+            ended_at: None,
+            duration_ms: None,
+            error_class: None,
+            error_message: None,
+            artifacts: Map::new(),
+        }
+    }
+
+    fn end(&mut self, status: StepStatus) {
+        let now = Utc::now();
+        self.status = status;
+        self.ended_at = Some(now);
+        self.duration_ms = self
+            .started_at
+            .map(|started| (now - started).num_milliseconds().max(0) as u64);
+    }
+}
+
+// Ordered step records of a run. Steps are started one at a time; failures,
+// artifacts and passes apply to the step currently running.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Steps(pub Vec<TestStep>);
+
+impl Steps {
+    pub fn planned<S: AsRef<str>>(names: &[S]) -> Self {
+        Steps(names.iter().map(|name| TestStep::pending(name.as_ref())).collect())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TestStep> {
+        self.0.iter()
+    }
+
+    fn running(&mut self) -> Option<&mut TestStep> {
+        self.0.iter_mut().find(|step| step.status == StepStatus::Running)
+    }
+
+    fn named(&mut self, name: &str) -> &mut TestStep {
+        if let Some(index) = self.0.iter().position(|step| step.name == name) {
+            return &mut self.0[index];
+        }
+        self.0.push(TestStep::pending(name));
+        self.0.last_mut().unwrap()
+    }
+
+    // Starting a step that is already running (a run resumed after a restart)
+    // keeps its original start time
+    pub fn start(&mut self, name: &str) {
+        let step = self.named(name);
+        if step.status != StepStatus::Running {
+            step.status = StepStatus::Running;
+            step.started_at = Some(Utc::now());
+        }
+    }
+
+    pub fn artifact(&mut self, key: &str, value: Value) {
+        if let Some(step) = self.running() {
+            step.artifacts.insert(key.to_string(), value);
+        }
+    }
+
+    // Artifact of the step currently running
+    pub fn running_artifact(&self, key: &str) -> Option<&Value> {
+        self.0
+            .iter()
+            .find(|step| step.status == StepStatus::Running)
+            .and_then(|step| step.artifacts.get(key))
+    }
+
+    pub fn pass(&mut self) {
+        if let Some(step) = self.running() {
+            step.end(StepStatus::Passed);
+        }
+    }
+
+    pub fn fail(&mut self, class: &str, message: &str) {
+        if let Some(step) = self.running() {
+            step.error_class = Some(class.to_string());
+            step.error_message = Some(message.to_string());
+            step.end(if class == TIMEOUT_CLASS { StepStatus::TimedOut } else { StepStatus::Failed });
+        }
+    }
+
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        let step = self.named(name);
+        step.status = StepStatus::Skipped;
+        step.artifacts.insert("reason".to_string(), Value::String(reason.to_string()));
+    }
+
+    // Closes out a finished run: steps that never started are skipped and a
+    // step left running failed. Returns whether the run as a whole passed,
+    // which takes every step passing or being skipped, and the last one passing.
+    pub fn finish(&mut self) -> bool {
+        let mut failed_step: Option<String> = None;
+        for step in self.0.iter_mut() {
+            match step.status {
+                StepStatus::Pending => {
+                    step.status = StepStatus::Skipped;
+                    if let Some(failed) = &failed_step {
+                        step.artifacts
+                            .insert("reason".to_string(), Value::String(format!("{} did not pass", failed)));
+                    }
+                }
+                StepStatus::Running => {
+                    step.error_class = Some("step_interrupted".to_string());
+                    step.error_message = Some("The run ended while this step was running".to_string());
+                    step.end(StepStatus::Failed);
+                }
+                _ => {}
+            }
+            if matches!(step.status, StepStatus::Failed | StepStatus::TimedOut) && failed_step.is_none() {
+                failed_step = Some(step.name.clone());
+            }
+        }
+
+        failed_step.is_none()
+            && self.0.last().is_some_and(|step| step.status == StepStatus::Passed)
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{Context, Result, anyhow};
+use bip39::Mnemonic;
+use bitcoin::bip32::{DerivationPath, Xpriv};
+use bitcoin::key::CompressedPublicKey;
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::{Address, Network};
+use ed25519_dalek::SigningKey;
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use std::str::FromStr;
+
+// A BTC receiving address together with the path it was derived on
+#[derive(Debug, Clone)]
+pub struct BtcReceiveAddress {
+    pub address: String,
+    pub derivation_path: String,
+}
+
+// Standard Solana derivation path used by the borrower CLI
+pub const SOLANA_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+// BIP84 path for the first external address of the first account
+pub fn bip84_path(network: Network) -> String {
+    let coin_type = match network {
+        Network::Bitcoin => 0,
+        _ => 1,
+    };
+    format!("m/84'/{}'/0'/0/0", coin_type)
+}
+
+// Derives the BIP84 (P2WPKH) receiving address for the mnemonic on the given network
+pub fn derive_btc_address(mnemonic: &Mnemonic, network: Network) -> Result<BtcReceiveAddress> {
+    let secp = Secp256k1::new();
+    let seed = mnemonic.to_seed("");
+
+    let master = Xpriv::new_master(network, &seed).context("Failed to create master key")?;
+    let path_str = bip84_path(network);
+    let path = DerivationPath::from_str(&path_str).context("Invalid derivation path")?;
+    let child = master.derive_priv(&secp, &path).context("Failed to derive child key")?;
+
+    let public_key = CompressedPublicKey::from_private_key(&secp, &child.to_priv())
+        .map_err(|e| anyhow!("Failed to compute public key: {}", e))?;
+    let address = Address::p2wpkh(&public_key, network);
+
+    Ok(BtcReceiveAddress {
+        address: address.to_string(),
+        derivation_path: path_str,
+    })
+}
+
+// Derives the base58 Solana pubkey for the mnemonic on the standard Solana path
+pub fn derive_solana_pubkey(mnemonic: &Mnemonic) -> Result<String> {
+    let seed = mnemonic.to_seed("");
+    let secret = slip10_ed25519_derive(&seed, SOLANA_DERIVATION_PATH)?;
+    let signing_key = SigningKey::from_bytes(&secret);
+
+    Ok(bs58::encode(signing_key.verifying_key().to_bytes()).into_string())
+}
+
+// SLIP-0010 ed25519 derivation; ed25519 only supports hardened children
+fn slip10_ed25519_derive(seed: &[u8], path: &str) -> Result<[u8; 32]> {
+    let (mut key, mut chain_code) = slip10_split(hmac_sha512(b"ed25519 seed", &[seed]));
+
+    let mut segments = path.split('/');
+    if segments.next() != Some("m") {
+        return Err(anyhow!("Derivation path must start with 'm': {}", path));
+    }
+
+    for segment in segments {
+        let index = segment
+            .strip_suffix('\'')
+            .ok_or_else(|| anyhow!("Non-hardened segment '{}' in ed25519 path {}", segment, path))?
+            .parse::<u32>()
+            .with_context(|| format!("Invalid segment '{}' in path {}", segment, path))?;
+        if index >= HARDENED_OFFSET {
+            return Err(anyhow!("Segment '{}' out of range in path {}", segment, path));
+        }
+
+        let index_bytes = (index | HARDENED_OFFSET).to_be_bytes();
+        (key, chain_code) = slip10_split(hmac_sha512(&chain_code, &[&[0u8], &key, &index_bytes]));
+    }
+
+    Ok(key)
+}
+
+fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+fn slip10_split(digest: [u8; 64]) -> ([u8; 32], [u8; 32]) {
+    let mut key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    key.copy_from_slice(&digest[..32]);
+    chain_code.copy_from_slice(&digest[32..]);
+    (key, chain_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference mnemonic of the BIP84 test vectors
+    const BIP84_MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn mnemonic() -> Mnemonic {
+        Mnemonic::parse(BIP84_MNEMONIC).unwrap()
+    }
+
+    #[test]
+    fn bip84_mainnet_first_receive_address() {
+        let derived = derive_btc_address(&mnemonic(), Network::Bitcoin).unwrap();
+        assert_eq!(derived.derivation_path, "m/84'/0'/0'/0/0");
+        assert_eq!(derived.address, "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+    }
+
+    #[test]
+    fn bip84_testnet_first_receive_address() {
+        let derived = derive_btc_address(&mnemonic(), Network::Testnet).unwrap();
+        assert_eq!(derived.derivation_path, "m/84'/1'/0'/0/0");
+        assert_eq!(derived.address, "tb1q6rz28mcfaxtmd6v789l9rrlrusdprr9pqcpvkl");
+    }
+
+    // SLIP-0010 test vector 1 for ed25519
+    #[test]
+    fn slip10_ed25519_test_vector_1() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let cases = [
+            ("m", "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"),
+            ("m/0'", "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"),
+            ("m/0'/1'", "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2"),
+            ("m/0'/1'/2'", "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9"),
+            ("m/0'/1'/2'/2'", "30d1dc7e5fc04c31219ab25a27ae00b50f6fd66622f6e9c913253d6511d1e662"),
+            ("m/0'/1'/2'/2'/1000000000'", "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793"),
+        ];
+        for (path, private_key) in cases {
+            assert_eq!(hex::encode(slip10_ed25519_derive(&seed, path).unwrap()), private_key, "{}", path);
+        }
+
+        let key = slip10_ed25519_derive(&seed, "m/0'").unwrap();
+        assert_eq!(
+            hex::encode(SigningKey::from_bytes(&key).verifying_key().to_bytes()),
+            "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c"
+        );
+    }
+
+    #[test]
+    fn slip10_rejects_non_hardened_paths() {
+        assert!(slip10_ed25519_derive(&[0u8; 16], "m/0").is_err());
+        assert!(slip10_ed25519_derive(&[0u8; 16], "0'/1'").is_err());
+        assert!(slip10_ed25519_derive(&[0u8; 16], "m/2147483648'").is_err());
+    }
+
+    // Pubkey `solana-keygen recover 'prompt:?key=0/0'` restores from the reference
+    // mnemonic: the path the borrower CLI's wallet derives its signing key on
+    #[test]
+    fn solana_pubkey_matches_cli_wallet() {
+        assert_eq!(
+            derive_solana_pubkey(&mnemonic()).unwrap(),
+            "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{Context, Result, anyhow};
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_RETENTION_HOURS: u64 = 24;
+
+// Run directories in use, which the retention sweep must leave alone
+fn active_dirs() -> &'static Mutex<HashSet<PathBuf>> {
+    static ACTIVE: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    ACTIVE.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+// Parent of all run directories: RUNS_DIR, or lava-runs under the system temp directory
+pub fn runs_root() -> PathBuf {
+    env::var("RUNS_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir().join("lava-runs"))
+}
+
+// How long finished run directories are kept (RUN_DIR_RETENTION_HOURS, 0 removes them right away)
+pub fn retention() -> Duration {
+    let hours = env::var("RUN_DIR_RETENTION_HOURS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_HOURS);
+    Duration::from_secs(hours.saturating_mul(3600))
+}
+
+// Run ids come from clients, so only keep characters that are safe in a file name.
+// An id that had to be changed gets a hash of the original after a `~`, which
+// no kept id contains, so "a/b" and "a_b" still get separate directories.
+fn dir_name(run_id: &str) -> String {
+    let name: String = run_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    let name = if name.is_empty() || name.starts_with('.') {
+        format!("_{}", name)
+    } else {
+        name
+    };
+    if name == run_id {
+        return name;
+    }
+    let hash = hex::encode(Sha256::digest(run_id.as_bytes()));
+    format!("{}~{}", name, &hash[..12])
+}
+
+// Scratch directory owned by a single run. The CLI runs inside it (it is also
+// the CLI's HOME, so any state the CLI keeps lands here), output files go to
+// `output/` and step logs to `logs/`.
+pub struct RunDir {
+    root: PathBuf,
+    paused: bool,
+}
+
+impl RunDir {
+    pub fn create(run_id: &str) -> Result<Self> {
+        let root = runs_root().join(dir_name(run_id));
+        if !active_dirs().lock().unwrap().insert(root.clone()) {
+            return Err(anyhow!("Run directory {} is already in use", root.display()));
+        }
+        let run_dir = Self { root, paused: false };
+
+        // Never let a rerun of the same id pick up stale files
+        if run_dir.root.exists() {
+            fs::remove_dir_all(&run_dir.root)
+                .with_context(|| format!("Failed to clear {}", run_dir.root.display()))?;
+        }
+        for dir in [run_dir.root.join("output"), run_dir.root.join("logs")] {
+            fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        Ok(run_dir)
+    }
+
+    // Picks up the directory of a run that continues after a pause, e.g. one
+    // waiting for its loan to mature. Files from before the pause are kept.
+    pub fn resume(run_id: &str) -> Result<Self> {
+        let root = runs_root().join(dir_name(run_id));
+        if !active_dirs().lock().unwrap().insert(root.clone()) {
+            return Err(anyhow!("Run directory {} is already in use", root.display()));
+        }
+        let run_dir = Self { root, paused: false };
+
+        for dir in [run_dir.root.join("output"), run_dir.root.join("logs")] {
+            fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        Ok(run_dir)
+    }
+
+    // Keeps the directory when it is released, because the run pauses and
+    // resumes in it later
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn output_file(&self, name: &str) -> PathBuf {
+        self.root.join("output").join(name)
+    }
+
+    // Writes the log of one step, replacing an earlier log of the same step
+    pub fn write_log(&self, step: &str, content: &str) -> Result<PathBuf> {
+        let path = self.root.join("logs").join(format!("{}.log", step));
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+// Releases the directory, removing it right away when retention is zero
+// unless the run is paused
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        active_dirs().lock().unwrap().remove(&self.root);
+        if retention().is_zero() && !self.paused {
+            if let Err(e) = fs::remove_dir_all(&self.root) {
+                warn!("Failed to remove run directory {}: {}", self.root.display(), e);
+            }
+        }
+    }
+}
+
+// Latest modification time of the directory and its immediate children
+fn last_modified(dir: &Path) -> Option<SystemTime> {
+    let mut latest = fs::metadata(dir).and_then(|meta| meta.modified()).ok()?;
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if let Ok(modified) = entry.metadata().and_then(|meta| meta.modified()) {
+                latest = latest.max(modified);
+            }
+        }
+    }
+    Some(latest)
+}
+
+// Removes run directories that have not changed within the retention period,
+// except those of the given paused runs
+pub fn sweep_expired(paused_run_ids: &HashSet<String>) -> Result<usize> {
+    let root = runs_root();
+    let paused = paused_run_ids.iter().map(|run_id| root.join(dir_name(run_id))).collect();
+    sweep(&root, retention(), &paused)
+}
+
+fn sweep(root: &Path, retention: Duration, paused: &HashSet<PathBuf>) -> Result<usize> {
+    if !root.exists() {
+        return Ok(0);
+    }
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+
+    for entry in fs::read_dir(root).with_context(|| format!("Failed to list {}", root.display()))? {
+        let path = entry?.path();
+        if !path.is_dir() || paused.contains(&path) || active_dirs().lock().unwrap().contains(&path) {
+            continue;
+        }
+
+        let expired = last_modified(&path)
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age >= retention);
+        if expired {
+            match fs::remove_dir_all(&path) {
+                Ok(()) => removed += 1,
+                Err(e) => warn!("Failed to remove run directory {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    if removed > 0 {
+        info!("Removed {} expired run directories from {}", removed, root.display());
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // All tests share one RUNS_DIR and use distinct run ids
+    fn test_root() -> PathBuf {
+        static ROOT: OnceLock<PathBuf> = OnceLock::new();
+        ROOT.get_or_init(|| {
+            let root = env::temp_dir().join(format!("lava-workdir-test-{}", uuid::Uuid::new_v4()));
+            env::set_var("RUNS_DIR", &root);
+            root
+        })
+        .clone()
+    }
+
+    #[test]
+    fn concurrent_runs_get_separate_directories() {
+        let root = test_root();
+        let runs: Vec<_> = (0..8)
+            .map(|index| {
+                std::thread::spawn(move || {
+                    let run_id = format!("concurrent-{}", index);
+                    let run_dir = RunDir::create(&run_id).unwrap();
+                    fs::write(run_dir.output_file("contract.json"), &run_id).unwrap();
+                    run_dir.write_log("borrow-init", &run_id).unwrap();
+                    std::thread::sleep(Duration::from_millis(20));
+                    (run_id, run_dir)
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        let paths: HashSet<_> = runs.iter().map(|(_, run_dir)| run_dir.path().to_path_buf()).collect();
+        assert_eq!(paths.len(), runs.len());
+        for (run_id, run_dir) in &runs {
+            assert_eq!(run_dir.path(), root.join(run_id));
+            assert_eq!(fs::read_to_string(run_dir.output_file("contract.json")).unwrap(), *run_id);
+            assert_eq!(fs::read_to_string(run_dir.path().join("logs/borrow-init.log")).unwrap(), *run_id);
+        }
+    }
+
+    #[test]
+    fn refuses_a_run_id_that_is_already_active() {
+        test_root();
+        let first = RunDir::create("active-run").unwrap();
+        fs::write(first.output_file("contract.json"), "first").unwrap();
+
+        let error = RunDir::create("active-run").err().unwrap();
+        assert!(error.to_string().contains("already in use"), "{}", error);
+        assert!(RunDir::resume("active-run").is_err());
+        assert_eq!(fs::read_to_string(first.output_file("contract.json")).unwrap(), "first");
+
+        // Once released, a rerun of the id starts from an empty directory
+        drop(first);
+        let rerun = RunDir::create("active-run").unwrap();
+        assert!(!rerun.output_file("contract.json").exists());
+    }
+
+    #[test]
+    fn resume_keeps_files_from_before_the_pause() {
+        test_root();
+        let paused = RunDir::create("paused-run").unwrap();
+        fs::write(paused.output_file("contract.json"), "paused").unwrap();
+        drop(paused);
+
+        let resumed = RunDir::resume("paused-run").unwrap();
+        assert_eq!(fs::read_to_string(resumed.output_file("contract.json")).unwrap(), "paused");
+    }
+
+    #[test]
+    fn hostile_run_ids_stay_inside_the_runs_directory() {
+        for (run_id, sanitized) in [
+            ("../../etc", "_.._.._etc~"),
+            (".hidden", "_.hidden~"),
+            ("", "_~"),
+            ("run 1/$(id)", "run_1___id_~"),
+        ] {
+            let name = dir_name(run_id);
+            assert!(name.starts_with(sanitized), "{}", name);
+            assert_eq!(name.len(), sanitized.len() + 12, "{}", name);
+        }
+        assert_eq!(dir_name("matrix-v1.4.2_b"), "matrix-v1.4.2_b");
+    }
+
+    #[test]
+    fn sanitized_run_ids_do_not_collide() {
+        let names: HashSet<String> = ["a/b", "a_b", "a b", "a\\b", "_", "", ".b", "_.b"]
+            .iter()
+            .map(|run_id| dir_name(run_id))
+            .collect();
+        assert_eq!(names.len(), 8);
+        assert_eq!(dir_name("a_b"), "a_b");
+        assert_eq!(dir_name("a/b"), dir_name("a/b"));
+    }
+
+    #[test]
+    fn sweep_removes_expired_directories_but_not_active_ones() {
+        let root = env::temp_dir().join(format!("lava-sweep-test-{}", uuid::Uuid::new_v4()));
+        for name in ["finished", "active"] {
+            fs::create_dir_all(root.join(name).join("logs")).unwrap();
+        }
+        fs::write(root.join("stray-file"), "").unwrap();
+        active_dirs().lock().unwrap().insert(root.join("active"));
+
+        assert_eq!(sweep(&root, Duration::from_secs(3600), &HashSet::new()).unwrap(), 0);
+        assert_eq!(sweep(&root, Duration::ZERO, &HashSet::new()).unwrap(), 1);
+        assert!(!root.join("finished").exists());
+        assert!(root.join("active").exists());
+        assert!(root.join("stray-file").exists());
+
+        active_dirs().lock().unwrap().remove(&root.join("active"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Backdates a directory and its immediate children, as the sweep looks at both
+    fn backdate(dir: &Path, by: Duration) {
+        let time = SystemTime::now() - by;
+        let children = fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path());
+        for path in std::iter::once(dir.to_path_buf()).chain(children) {
+            fs::File::open(&path).unwrap().set_modified(time).unwrap();
+        }
+    }
+
+    #[test]
+    fn paused_run_keeps_its_directory_until_it_resumes() {
+        let root = test_root();
+        let mut paused = RunDir::create("maturing-run").unwrap();
+        fs::write(paused.path().join("cli-state"), "before the pause").unwrap();
+        paused.pause();
+        drop(paused);
+        let finished = RunDir::create("finished-run").unwrap();
+        drop(finished);
+
+        // Both outlived the retention period while the loan matured
+        for run_id in ["maturing-run", "finished-run"] {
+            backdate(&root.join(run_id), retention() * 2);
+        }
+        assert_eq!(sweep_expired(&HashSet::from(["maturing-run".to_string()])).unwrap(), 1);
+        assert!(!root.join("finished-run").exists());
+
+        let resumed = RunDir::resume("maturing-run").unwrap();
+        assert_eq!(fs::read_to_string(resumed.path().join("cli-state")).unwrap(), "before the pause");
+    }
+}
@@ -0,0 +1,310 @@
+// End-to-end runs against the offline mock stack
+mod support;
+
+use serde_json::json;
+use support::{MockStack, TestServer, step};
+
+#[tokio::test]
+async fn standard_run_passes_against_the_mock_stack() {
+    let mock = MockStack::start(&[]);
+    let server = TestServer::start(&mock, &[]);
+
+    let result = server.run_test(json!({ "seed": 7 })).await;
+
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["profile"], "mock");
+    assert_eq!(result["error"], serde_json::Value::Null);
+    assert!(result["contract_id"].as_str().is_some_and(|id| id.len() == 32), "{}", result);
+    assert!(result["collateral_repayment_txid"].as_str().is_some(), "{}", result);
+    let lava_usd = &result["run_details"]["lava_usd_balance"];
+    assert_eq!(lava_usd["before"]["amount"], 0, "{}", lava_usd);
+    assert_eq!(lava_usd["after"]["amount"], 10_000_000, "{}", lava_usd);
+    for name in ["btc-faucet", "lava-usd-faucet", "btc-funding", "lava-usd-funding", "cli-install", "borrow-init", "borrow-repay", "get-contract", "verify-contract"] {
+        assert_eq!(step(&result, name)["status"], "passed", "{}: {}", name, result);
+    }
+}
+
+// A provided mnemonic must not show up in the server log or the run's CLI logs
+#[tokio::test]
+async fn provided_mnemonic_never_reaches_the_logs() {
+    let mnemonic = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+    let mock = MockStack::start(&[]);
+    let server = TestServer::start(&mock, &[]);
+
+    let result = server.run_test(json!({ "run_id": "redacted-run", "mnemonic": mnemonic })).await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["mnemonic"], "[REDACTED]");
+
+    let log = server.log();
+    assert!(log.contains("redacted-run"), "server log was not captured");
+    assert!(log.contains("Executing command"), "CLI invocations were not logged");
+    let mut cli_logs = String::new();
+    for entry in std::fs::read_dir(server.runs_dir().join("redacted-run").join("logs")).unwrap() {
+        cli_logs.push_str(&std::fs::read_to_string(entry.unwrap().path()).unwrap());
+    }
+    assert!(!cli_logs.is_empty());
+
+    for (name, text) in [("server log", &log), ("CLI logs", &cli_logs)] {
+        assert!(!text.contains(mnemonic), "mnemonic in {}", name);
+        assert!(!text.contains("legal winner thank year"), "mnemonic words in {}", name);
+    }
+}
+
+// Faucet calls survive transient 503s; the contract JSON stays in `details`
+// and the attempts are recorded in `run_details`
+#[tokio::test]
+async fn faucet_failures_are_retried() {
+    let mock = MockStack::start(&[("MOCK_FAUCET_FAIL_FIRST", "2")]);
+    let server = TestServer::start(&mock, &[]);
+
+    let result = server.run_test(json!({})).await;
+
+    assert_eq!(result["success"], true, "{}", result);
+    let btc_attempts = result["run_details"]["faucet_attempts"]["btc"].as_array().unwrap();
+    let statuses: Vec<_> = btc_attempts.iter().map(|attempt| attempt["status"].clone()).collect();
+    assert_eq!(statuses, [json!(503), json!(503), json!(200)]);
+    assert_eq!(btc_attempts[0]["retry_after_ms"], 1000);
+    assert_eq!(result["run_details"]["faucet_attempts"]["lava_usd"].as_array().unwrap().len(), 3);
+
+    assert!(result["details"]["Closed"].is_object(), "{}", result["details"]);
+    assert!(result["details"]["outcome"]["repayment"]["collateral_repayment_txid"].is_string());
+    assert!(result["details"].get("faucet_attempts").is_none());
+}
+
+// A replayed seed reuses the address; the second run must wait for its own
+// faucet UTXO instead of passing on the first run's
+#[tokio::test]
+async fn replayed_wallet_needs_new_funding() {
+    let mock = MockStack::start(&[]);
+    let server = TestServer::start(&mock, &[]);
+
+    let first = server.run_test(json!({ "seed": 99 })).await;
+    let second = server.run_test(json!({ "seed": 99 })).await;
+
+    assert_eq!(first["btc_address"], second["btc_address"]);
+    for result in [&first, &second] {
+        assert_eq!(result["success"], true, "{}", result);
+    }
+    assert_ne!(first["funding_txid"], second["funding_txid"]);
+    assert_eq!(step(&first, "btc-faucet")["artifacts"]["existing_utxos"], 0);
+    assert_eq!(step(&second, "btc-faucet")["artifacts"]["existing_utxos"], 1);
+}
+
+// Without the balance before funding, tokens already held could pass for the
+// transfer, so the LavaUSD funding check fails instead of assuming zero
+#[tokio::test]
+async fn unreadable_lava_usd_balance_fails_the_funding_check() {
+    let mock = MockStack::start(&[]);
+    let profiles = std::env::temp_dir().join(format!("lava-it-profiles-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&profiles, mock.profiles_toml().replace("/solana\"", "/no-solana\"")).unwrap();
+    let server = TestServer::start(&mock, &[("NETWORK_PROFILES_FILE", profiles.to_str().unwrap())]);
+
+    let result = server.run_test(json!({})).await;
+    let _ = std::fs::remove_file(&profiles);
+
+    assert_eq!(result["success"], false, "{}", result);
+    assert_eq!(result["run_details"]["failure"]["class"], "lava_usd_balance_unknown", "{}", result);
+    assert_eq!(step(&result, "lava-usd-funding")["status"], "failed", "{}", result);
+    assert_eq!(step(&result, "cli-install")["status"], "skipped", "{}", result);
+}
+
+// Runs executing at the same time each get their own wallet, directory and contract
+#[tokio::test]
+async fn concurrent_runs_stay_isolated() {
+    let mock = MockStack::start(&[]);
+    let server = TestServer::start(&mock, &[("QUEUE_WORKERS", "3")]);
+
+    let run_ids = ["parallel-a", "parallel-b", "parallel-c"];
+    for run_id in run_ids {
+        let (status, response) = server.post("/run-test", json!({ "run_id": run_id })).await;
+        assert_eq!(status, 200, "{}", response);
+    }
+    let mut results = Vec::new();
+    for run_id in run_ids {
+        results.push(server.wait_for_result(run_id).await);
+    }
+
+    let mut contract_ids = std::collections::HashSet::new();
+    for (run_id, result) in run_ids.iter().zip(&results) {
+        assert_eq!(result["success"], true, "{}", result);
+        let work_dir = std::path::PathBuf::from(result["run_details"]["work_dir"].as_str().unwrap());
+        assert_eq!(work_dir, server.runs_dir().join(run_id));
+
+        // The contract file in each directory belongs to that run's contract
+        let contract_id = result["contract_id"].as_str().unwrap();
+        let outputs: Vec<_> = std::fs::read_dir(work_dir.join("output")).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert!(!outputs.is_empty());
+        for output in outputs {
+            let contract: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output).unwrap()).unwrap();
+            assert_eq!(contract["contract_id"], contract_id);
+        }
+        contract_ids.insert(contract_id.to_string());
+    }
+    assert_eq!(contract_ids.len(), run_ids.len());
+
+    // The three ran side by side rather than one after another
+    let started: Vec<_> = results.iter().map(|result| step(result, "borrow-init")["started_at"].as_str().unwrap().to_string()).collect();
+    let ended: Vec<_> = results.iter().map(|result| step(result, "verify-contract")["ended_at"].as_str().unwrap().to_string()).collect();
+    assert!(started.iter().max() < ended.iter().min(), "runs did not overlap: {:?} {:?}", started, ended);
+}
+
+// Runs the standard scenario with the fake CLI scripted to misbehave at one
+// step and checks the run fails there with the given class
+async fn assert_cli_failure(script: &str, failing_step: &str, class: &str) {
+    let mock = MockStack::start(&[("MOCK_CLI_SCRIPT", script)]);
+    let server = TestServer::start(&mock, &[]);
+
+    let result = server.run_test(json!({})).await;
+
+    assert_eq!(result["success"], false, "{}", result);
+    assert_eq!(result["run_details"]["failure"]["class"], class, "{}", result);
+    assert!(result["error_message"].as_str().is_some_and(|error| error.starts_with(class)), "{}", result);
+    let step = step(&result, failing_step);
+    assert_eq!(step["status"], "failed", "{}", result);
+    assert_eq!(step["error_class"], class, "{}", result);
+}
+
+#[tokio::test]
+async fn non_zero_exit_fails_the_run() {
+    assert_cli_failure("borrow-init:fail", "borrow-init", "borrow_init_failed").await;
+}
+
+#[tokio::test]
+async fn missing_contract_id_fails_the_run() {
+    assert_cli_failure("borrow-init:no-contract-id", "borrow-init", "contract_id_missing").await;
+}
+
+#[tokio::test]
+async fn missing_output_file_fails_the_run() {
+    assert_cli_failure("get-contract:no-file", "get-contract", "contract_file_missing").await;
+}
+
+#[tokio::test]
+async fn invalid_contract_json_fails_the_run() {
+    assert_cli_failure("get-contract:bad-json", "get-contract", "contract_json_invalid").await;
+}
+
+#[tokio::test]
+async fn contract_left_open_after_repay_fails_the_run() {
+    assert_cli_failure("borrow-repay:no-close", "verify-contract", "contract_state_mismatch").await;
+}
+
+// Requests racing with the same run id get one run; the others are refused
+// with 409 rather than failing on the queue's unique constraint
+#[tokio::test]
+async fn concurrent_duplicate_run_ids_are_refused() {
+    let mock = MockStack::start(&[]);
+    let server = TestServer::start(&mock, &[]);
+
+    let body = json!({ "run_id": "same-id" });
+    let responses = tokio::join!(
+        server.post("/run-test", body.clone()),
+        server.post("/run-test", body.clone()),
+        server.post("/run-test", body.clone()),
+        server.post("/run-test", body.clone()),
+        server.post("/run-test", body.clone()),
+    );
+    let mut statuses = vec![responses.0 .0, responses.1 .0, responses.2 .0, responses.3 .0, responses.4 .0];
+    statuses.sort();
+    assert_eq!(statuses, [200, 409, 409, 409, 409]);
+
+    let result = server.wait_for_result("same-id").await;
+    assert_eq!(result["success"], true, "{}", result);
+    let (status, _) = server.post("/run-test", body).await;
+    assert_eq!(status, 200, "a finished run id can be reused");
+}
+
+// A queued job that can no longer be decoded ends as a failed run instead of
+// staying "running"
+#[tokio::test]
+async fn undecodable_queued_run_is_stored_as_failed() {
+    let mock = MockStack::start(&[]);
+    let server = TestServer::start(&mock, &[]);
+
+    // The single worker is busy with the first run while the second waits
+    let (status, response) = server.post("/run-test", json!({ "run_id": "busy" })).await;
+    assert_eq!(status, 200, "{}", response);
+    let (status, response) = server.post("/run-test", json!({ "run_id": "broken" })).await;
+    assert_eq!(status, 200, "{}", response);
+
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", server.dir.join("tests.db").display())).await.unwrap();
+    let updated = sqlx::query("UPDATE run_queue SET job = '{\"Test\": 42}' WHERE run_id = 'broken' AND started_at IS NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(updated.rows_affected(), 1, "the run was claimed before its job could be replaced");
+    pool.close().await;
+
+    let result = server.wait_for_result("broken").await;
+    assert_eq!(result["success"], false, "{}", result);
+    assert_eq!(result["run_details"]["failure"]["class"], "run_unreadable", "{}", result);
+    assert!(result["error_message"].as_str().is_some_and(|error| error.starts_with("run_unreadable: ")), "{}", result);
+    assert_eq!(server.wait_for_result("busy").await["success"], true);
+}
+
+// An error outside the steps is recorded as a failure class, not as contract details
+#[tokio::test]
+async fn run_that_cannot_start_is_stored_as_failed() {
+    let mock = MockStack::start(&[]);
+    let server = TestServer::start(&mock, &[("RUNS_DIR", "/dev/null/runs")]);
+
+    let result = server.run_test(json!({ "run_id": "no-run-dir" })).await;
+    assert_eq!(result["success"], false, "{}", result);
+    assert_eq!(result["run_details"]["failure"]["class"], "run_failed", "{}", result);
+    assert!(result["error_message"].as_str().is_some_and(|error| error.starts_with("run_failed: ")), "{}", result);
+    assert_eq!(result["details"], serde_json::Value::Null, "{}", result);
+}
+
+// With directories removed as soon as a run ends, a run paused until its loan
+// matures keeps its directory through the pause and other runs' sweeps, and
+// resumes in it
+#[tokio::test]
+async fn paused_run_keeps_its_directory_until_it_resumes() {
+    let suites = std::env::temp_dir().join(format!("lava-it-suites-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &suites,
+        r#"[suites.quick-expiry]
+[[suites.quick-expiry.scenarios]]
+name = "expiry"
+loan_duration_days = 1
+steps = ["borrow-init", "await-maturity"]
+settle_secs = 0
+maturity_poll_secs = 2
+expect = { state = "Closed", outcome = "default", outcome_fields = ["collateral_liquidation_txid"], absent_outcomes = ["repayment"] }
+"#,
+    )
+    .unwrap();
+    let mock = MockStack::start(&[("MOCK_SECONDS_PER_DAY", "60")]);
+    let server = TestServer::start(&mock, &[
+        ("SUITES_FILE", suites.to_str().unwrap()),
+        ("RUN_DIR_RETENTION_HOURS", "0"),
+        ("MATURITY_CHECK_SECS", "1"),
+    ]);
+
+    let (status, response) = server.post("/run-suite/quick-expiry", json!({ "run_id": "maturing" })).await;
+    assert_eq!(status, 200, "{}", response);
+    let paused = loop {
+        let (_, result) = server.get("/test-status/maturing-expiry").await;
+        if result["status"] == "awaiting maturity" {
+            break result;
+        }
+        assert_ne!(result["status"], "completed", "run finished without pausing: {}", result);
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    };
+    let work_dir = server.runs_dir().join("maturing-expiry");
+    assert_eq!(paused["run_details"]["work_dir"], json!(work_dir));
+    assert!(work_dir.join("logs").join("borrow-init.log").exists(), "paused run lost its directory");
+
+    // Every run sweeps expired directories before it starts
+    let other = server.run_test(json!({ "run_id": "sweeper" })).await;
+    assert_eq!(other["success"], true, "{}", other);
+    assert!(!server.runs_dir().join("sweeper").exists());
+    let (_, still_paused) = server.get("/test-status/maturing-expiry").await;
+    assert_eq!(still_paused["status"], "awaiting maturity", "loan matured before the sweep: {}", still_paused);
+    assert!(work_dir.join("logs").join("borrow-init.log").exists(), "sweep removed a paused run's directory");
+
+    let resumed = server.wait_for_result("maturing-expiry").await;
+    assert_eq!(resumed["success"], true, "{}", resumed);
+    assert!(step(&resumed, "await-maturity")["artifacts"]["polls"].as_u64().unwrap() > 1, "{}", resumed);
+    std::fs::remove_file(&suites).unwrap();
+}
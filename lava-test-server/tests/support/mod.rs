@@ -0,0 +1,214 @@
+// Starts the mock stack and the test server as child processes, each on a free
+// port with its own scratch directory, so integration tests run offline and in
+// parallel. Both are killed and the scratch directory removed when dropped.
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+pub const ADMIN_TOKEN: &str = "integration-admin-token";
+pub const MNEMONIC_KEY: &str = "6c6176616c6176616c6176616c6176616c6176616c6176616c6176616c617661";
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+const RUN_TIMEOUT: Duration = Duration::from_secs(180);
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn wait_for_port(port: u16, child: &mut Child, name: &str) {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    while TcpStream::connect(("127.0.0.1", port)).is_err() {
+        if let Ok(Some(status)) = child.try_wait() {
+            panic!("{} exited during startup: {}", name, status);
+        }
+        assert!(Instant::now() < deadline, "{} did not start listening on {}", name, port);
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn scratch_dir(prefix: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}-{}", prefix, uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub struct MockStack {
+    child: Child,
+    pub url: String,
+}
+
+impl MockStack {
+    // Starts lava-mock-stack with extra environment, e.g. MOCK_CLI_SCRIPT
+    pub fn start(env: &[(&str, &str)]) -> Self {
+        let port = free_port();
+        let mut child = Command::new(env!("CARGO_BIN_EXE_lava-mock-stack"))
+            .env("MOCK_BIND_ADDRESS", format!("127.0.0.1:{}", port))
+            .envs(env.iter().copied())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start lava-mock-stack");
+        wait_for_port(port, &mut child, "lava-mock-stack");
+        Self { child, url: format!("http://127.0.0.1:{}", port) }
+    }
+
+    // A "mock" network profile pointing at this instance instead of the default port
+    pub fn profiles_toml(&self) -> String {
+        format!(
+            r#"[profiles.mock]
+btc_network = "regtest"
+faucet_base_url = "{url}/faucet"
+btc_funding_sats = 100000
+lava_usd_funding_amount = 10
+cli_network_flag = "--testnet"
+cli_url = "{url}/cli/loans-borrower-cli"
+explorer_url = "{url}/esplora"
+esplora_url = "{url}/esplora"
+funding_timeout_secs = 30
+chain_poll_interval_secs = 1
+solana_rpc_url = "{url}/solana"
+lava_usd_mint = "MockLavaUsdMint111111111111111111111111111"
+lava_usd_timeout_secs = 30
+borrow_init_timeout_secs = 30
+borrow_finalize_timeout_secs = 30
+borrow_repay_timeout_secs = 30
+get_contract_timeout_secs = 30
+"#,
+            url = self.url
+        )
+    }
+}
+
+impl Drop for MockStack {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct TestServer {
+    child: Child,
+    pub url: String,
+    pub dir: PathBuf,
+    client: reqwest::Client,
+}
+
+impl TestServer {
+    // Starts lava-test-server against the mock stack with the "mock" profile as
+    // default; its log goes to `server.log` in the scratch directory
+    pub fn start(mock: &MockStack, env: &[(&str, &str)]) -> Self {
+        let dir = scratch_dir("lava-it");
+        let profiles = dir.join("profiles.toml");
+        fs::write(&profiles, mock.profiles_toml()).unwrap();
+
+        let port = free_port();
+        let log = fs::File::create(dir.join("server.log")).unwrap();
+        let mut child = Command::new(env!("CARGO_BIN_EXE_lava-test-server"))
+            .current_dir(&dir)
+            .env("BIND_ADDRESS", format!("127.0.0.1:{}", port))
+            .env("DATABASE_URL", format!("sqlite:{}?mode=rwc", dir.join("tests.db").display()))
+            .env("NETWORK_PROFILES_FILE", &profiles)
+            .env("DEFAULT_NETWORK_PROFILE", "mock")
+            .env("MOCK_STACK_URL", &mock.url)
+            .env("RUNS_DIR", dir.join("runs"))
+            .env("CLI_CACHE_DIR", dir.join("cli-cache"))
+            .env("MNEMONIC_KEY", MNEMONIC_KEY)
+            .env("ADMIN_TOKEN", ADMIN_TOKEN)
+            .env("FAUCET_BASE_DELAY_MS", "100")
+            .env("RUST_LOG", "debug")
+            .envs(env.iter().copied())
+            .stdout(log.try_clone().unwrap())
+            .stderr(log)
+            .spawn()
+            .expect("failed to start lava-test-server");
+        wait_for_port(port, &mut child, "lava-test-server");
+
+        Self {
+            child,
+            url: format!("http://127.0.0.1:{}", port),
+            dir,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn log(&self) -> String {
+        fs::read_to_string(self.dir.join("server.log")).unwrap_or_default()
+    }
+
+    pub fn runs_dir(&self) -> PathBuf {
+        self.dir.join("runs")
+    }
+
+    // POSTs JSON as the admin and returns the status code and body
+    pub async fn post(&self, path: &str, body: Value) -> (u16, Value) {
+        let response = self
+            .client
+            .post(format!("{}{}", self.url, path))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        let status = response.status().as_u16();
+        (status, response.json().await.unwrap_or(Value::Null))
+    }
+
+    pub async fn get(&self, path: &str) -> (u16, Value) {
+        let response = self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        let status = response.status().as_u16();
+        (status, response.json().await.unwrap_or(Value::Null))
+    }
+
+    // Starts a run and waits for its result
+    pub async fn run_test(&self, body: Value) -> Value {
+        let (status, response) = self.post("/run-test", body).await;
+        assert_eq!(status, 200, "{}", response);
+        self.wait_for_result(response["run_id"].as_str().unwrap()).await
+    }
+
+    // Polls /test-status until the run has left the queue and finished
+    pub async fn wait_for_result(&self, run_id: &str) -> Value {
+        let deadline = Instant::now() + RUN_TIMEOUT;
+        loop {
+            let (status, result) = self.get(&format!("/test-status/{}", run_id)).await;
+            if status == 200 && result["status"] == "completed" {
+                return result;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "run {} did not finish: {}\n{}",
+                run_id,
+                result,
+                self.log()
+            );
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// The step of a result with the given name
+pub fn step<'a>(result: &'a Value, name: &str) -> &'a Value {
+    result["steps"]
+        .as_array()
+        .and_then(|steps| steps.iter().find(|step| step["name"] == name))
+        .unwrap_or_else(|| panic!("no step {} in {}", name, result))
+}